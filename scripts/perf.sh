@@ -0,0 +1,3 @@
+#!/bin/sh
+docker run -it --rm -v /root/workspace/Sysy-Cargo-Compiler:/root/compiler maxxing/compiler-dev \
+  autotest -perf -s perf /root/compiler
\ No newline at end of file
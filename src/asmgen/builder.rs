@@ -1,16 +1,15 @@
 use super::func::FunctionInfo;
-use std::fs::File;
 use std::io::{Result, Write};
 
 /// Assembly builder.
 pub struct AsmBuilder<'f> {
-  f: &'f mut File,
+  f: &'f mut dyn Write,
   temp: &'static str,
 }
 
 impl<'f> AsmBuilder<'f> {
     /// Creates a new assembly builder.
-    pub fn new(f: &'f mut File, temp: &'static str) -> Self {
+    pub fn new(f: &'f mut dyn Write, temp: &'static str) -> Self {
       Self { f, temp }
     }
   
@@ -86,6 +85,24 @@ impl<'f> AsmBuilder<'f> {
       }
     }
   
+    pub fn fsw(&mut self, src: &str, addr: &str, offset: i32) -> Result<()> {
+      if (-2048..=2047).contains(&offset) {
+        writeln!(self.f, "  fsw {src}, {offset}({addr})")
+      } else {
+        self.addi(self.temp, addr, offset)?;
+        writeln!(self.f, "  fsw {src}, 0({})", self.temp)
+      }
+    }
+  
+    pub fn flw(&mut self, dest: &str, addr: &str, offset: i32) -> Result<()> {
+      if (-2048..=2047).contains(&offset) {
+        writeln!(self.f, "  flw {dest}, {offset}({addr})")
+      } else {
+        self.addi(self.temp, addr, offset)?;
+        writeln!(self.f, "  flw {dest}, 0({})", self.temp)
+      }
+    }
+  
     pub fn bnez(&mut self, cond: &str, label: &str) -> Result<()> {
       writeln!(self.f, "  bnez {cond}, {label}")
     }
@@ -110,6 +127,10 @@ impl<'f> AsmBuilder<'f> {
         if !info.is_leaf() {
           self.sw("ra", "sp", offset - 4)?;
         }
+        // save callee-saved registers
+        for (i, reg) in info.saved_regs().iter().enumerate() {
+          self.sw(reg, "sp", info.saved_reg_offset(i))?;
+        }
       }
       Ok(())
     }
@@ -120,6 +141,10 @@ impl<'f> AsmBuilder<'f> {
         if !info.is_leaf() {
           self.lw("ra", "sp", offset - 4)?;
         }
+        // restore callee-saved registers
+        for (i, reg) in info.saved_regs().iter().enumerate() {
+          self.lw(reg, "sp", info.saved_reg_offset(i))?;
+        }
         self.addi("sp", "sp", offset)?;
       }
       writeln!(self.f, "  ret")
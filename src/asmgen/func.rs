@@ -1,5 +1,8 @@
+use super::regalloc::CALLEE_SAVED;
+use super::values::LocalValue;
+use crate::trace::{self, Phase};
 use koopa::ir::entities::ValueData;
-use koopa::ir::{BasicBlock, Function, TypeKind, ValueKind};
+use koopa::ir::{BasicBlock, Function, FunctionData, TypeKind, Value, ValueKind};
 use std::cell::Cell;
 use std::collections::HashMap;
 
@@ -11,13 +14,16 @@ pub struct FunctionInfo {
   max_arg_num: Option<usize>,
   alloc_size: usize,
   allocs: HashMap<*const ValueData, Slot>,
+  regs: HashMap<*const ValueData, &'static str>,
+  /// Callee-saved registers used by the function.
+  saved_regs: Vec<&'static str>,
   bbs: HashMap<BasicBlock, String>,
   sp_offset: Cell<Option<usize>>,
 }
 
 impl FunctionInfo {
     thread_local! {
-      static NEXT_TEMP_LABEL_ID: Cell<usize> = Cell::new(0);
+      static NEXT_TEMP_LABEL_ID: Cell<usize> = const { Cell::new(0) };
     }
   
     /// Creates a new function information.
@@ -27,6 +33,8 @@ impl FunctionInfo {
         max_arg_num: None,
         alloc_size: 0,
         allocs: HashMap::new(),
+        regs: HashMap::new(),
+        saved_regs: Vec::new(),
         bbs: HashMap::new(),
         sp_offset: Cell::new(None),
       }
@@ -53,31 +61,64 @@ impl FunctionInfo {
     pub fn alloc_slot(&mut self, value: &ValueData) {
       match value.kind() {
         ValueKind::Alloc(_) => {
-          self.allocs.insert(value, Slot::new(self.alloc_size, false));
+          self.allocs.insert(value, Slot::new(self.alloc_size, true));
           self.alloc_size += match value.ty().kind() {
             TypeKind::Pointer(base) => base.size(),
             _ => unreachable!(),
           };
         }
         _ => {
-          let is_ptr = matches!(value.ty().kind(), TypeKind::Pointer(_));
-          let slot = Slot::new(self.alloc_size, is_ptr);
-          self.allocs.insert(value, slot);
+          self.allocs.insert(value, Slot::new(self.alloc_size, false));
           self.alloc_size += value.ty().size();
         }
       };
     }
-  
+
+    /// Assigns a register to the given value data.
+    pub fn assign_reg(&mut self, value: &ValueData, reg: &'static str) {
+      self.regs.insert(value, reg);
+      if CALLEE_SAVED.contains(&reg) && !self.saved_regs.contains(&reg) {
+        self.saved_regs.push(reg);
+        self.saved_regs.sort_by_key(|r| CALLEE_SAVED.iter().position(|s| s == r));
+      }
+    }
+
+    /// Returns the callee-saved registers used by the function.
+    pub fn saved_regs(&self) -> &[&'static str] {
+      &self.saved_regs
+    }
+
+    /// Returns the offset (relative to `sp`) of the slot
+    /// for saving the `index`-th callee-saved register.
+    pub fn saved_reg_offset(&self, index: usize) -> i32 {
+      let ra = if self.is_leaf() { 0 } else { 4 };
+      (self.sp_offset() - ra - (index + 1) * 4) as i32
+    }
+
     /// Returns the slot offset (relative to `sp`) of the given value data.
     pub fn slot_offset(&self, value: &ValueData) -> Option<Slot> {
       self.allocs.get(&(value as *const ValueData)).map(|&offset| {
-        if self.is_leaf() {
-          offset.map(|o| self.sp_offset() - self.alloc_size + o)
-        } else {
-          offset.map(|o| self.sp_offset() - 4 - self.alloc_size + o)
-        }
+        let ra = if self.is_leaf() { 0 } else { 4 };
+        let saved = self.saved_regs.len() * 4;
+        offset.map(|o| self.sp_offset() - ra - saved - self.alloc_size + o)
       })
     }
+
+    /// Returns the location of the given local value data,
+    /// `None` if the value is not used.
+    pub fn local_value(&self, value: &ValueData) -> Option<LocalValue> {
+      if let Some(&reg) = self.regs.get(&(value as *const ValueData)) {
+        return Some(LocalValue::Reg(reg));
+      }
+      match value.kind() {
+        // arguments after the 8th are passed on the stack of the caller
+        ValueKind::FuncArgRef(arg) if arg.index() >= 8 => {
+          let offset = self.sp_offset() + (arg.index() - 8) * 4;
+          Some(LocalValue::Local(Slot::new(offset, false)))
+        }
+        _ => self.slot_offset(value).map(LocalValue::Local),
+      }
+    }
   
     /// Logs basic block name.
     pub fn log_bb_name(&mut self, bb: BasicBlock, name: &Option<String>) {
@@ -89,6 +130,12 @@ impl FunctionInfo {
       self.bbs.insert(bb, name);
     }
   
+    /// Returns a new temporary label.
+    pub fn temp_label() -> String {
+      let id = Self::NEXT_TEMP_LABEL_ID.with(|id| id.replace(id.get() + 1));
+      format!(".L{}", id)
+    }
+
     /// Returns a reference to the name of the given basic block.
     pub fn bb_name(&self, bb: BasicBlock) -> &str {
       self.bbs.get(&bb).as_ref().unwrap()
@@ -101,33 +148,93 @@ impl FunctionInfo {
       } else {
         // slot for storing return address
         let ra = if self.is_leaf() { 0 } else { 4 };
+        // slots for storing callee-saved registers
+        let saved = self.saved_regs.len() * 4;
         // slot for storing arguments
         let args = match self.max_arg_num {
           Some(num) if num > 8 => (num - 8) * 4,
           _ => 0,
         };
         // the final offset
-        let offset = ra + self.alloc_size + args;
+        let offset = ra + saved + self.alloc_size + args;
         // align to 16 bytes
-        let sp_offset = (offset + 15) / 16 * 16;
+        let sp_offset = offset.div_ceil(16) * 16;
         self.sp_offset.set(Some(sp_offset));
         sp_offset
       }
     }
+
+    /// Traces the stack frame layout, registers and labels of the function.
+    pub fn trace_layout(&self, data: &FunctionData) {
+      if !trace::is_enabled(Phase::Asmgen) {
+        return;
+      }
+      trace!(Asmgen, "function `{}`: frame size {}", &data.name()[1..], self.sp_offset());
+      if !self.is_leaf() {
+        trace!(Asmgen, "  sp+{}: ra", self.sp_offset() - 4);
+      }
+      for (i, reg) in self.saved_regs.iter().enumerate() {
+        trace!(Asmgen, "  sp+{}: saved {}", self.saved_reg_offset(i), reg);
+      }
+      let values = value_names(data);
+      let mut slots: Vec<_> = values
+        .iter()
+        .filter_map(|(v, name)| Some((self.slot_offset(data.dfg().value(*v))?, name)))
+        .collect();
+      slots.sort_by_key(|(slot, _)| slot.offset);
+      for (slot, name) in slots {
+        let kind = if slot.is_alloc { "alloc" } else { "spilled" };
+        trace!(Asmgen, "  sp+{}: {} {}", slot.offset, kind, name);
+      }
+      for (v, name) in &values {
+        if let Some(reg) = self.regs.get(&(data.dfg().value(*v) as *const ValueData)) {
+          trace!(Asmgen, "  {}: {}", name, reg);
+        }
+      }
+      for &bb in data.layout().bbs().keys() {
+        let name = data.dfg().bb(bb).name().as_deref().unwrap_or("<unnamed>");
+        trace!(Asmgen, "  label {}: {}", self.bb_name(bb), name);
+      }
+    }
   }
 
 
+/// Returns all local values of the given function in layout order,
+/// with their names in Koopa IR. Unnamed values are numbered from `%0`.
+fn value_names(data: &FunctionData) -> Vec<(Value, String)> {
+  let mut next_id = 0;
+  let mut name = |v: Value| {
+    let value = data.dfg().value(v);
+    value.name().clone().unwrap_or_else(|| {
+      next_id += 1;
+      format!("%{}", next_id - 1)
+    })
+  };
+  let mut values: Vec<_> = data.params().iter().map(|&p| (p, name(p))).collect();
+  for (&bb, node) in data.layout().bbs() {
+    values.extend(data.dfg().bb(bb).params().iter().map(|&p| (p, name(p))));
+    values.extend(
+      node
+        .insts()
+        .keys()
+        .filter(|&&i| !data.dfg().value(i).ty().is_unit())
+        .map(|&i| (i, name(i))),
+    );
+  }
+  values
+}
+
 #[derive(Clone, Copy)]
 pub struct Slot {
   pub offset: usize,
-  /// `true` if the slot stores an pointer but not an allocation.
-  pub is_ptr: bool,
+  /// `true` if the slot is an allocation but not a spilled value.
+  pub is_alloc: bool,
 }
 
 impl Slot {
   /// Creates a new stack slot.
-  fn new(offset: usize, is_ptr: bool) -> Self {
-    Self { offset, is_ptr }
+  fn new(offset: usize, is_alloc: bool) -> Self {
+    Self { offset, is_alloc }
   }
 
   /// Maps the offset by applying the given function.
@@ -137,7 +244,7 @@ impl Slot {
   {
     Self {
       offset: f(self.offset),
-      is_ptr: self.is_ptr,
+      is_alloc: self.is_alloc,
     }
   }
 }
\ No newline at end of file
@@ -2,18 +2,19 @@ use crate::asm_value;
 
 use super::builder::AsmBuilder;
 use super::info::ProgramInfo;
-use super::values::{AsmValue, LocalValue};
+use super::regalloc;
+use super::values::{parallel_move, AsmValue, LocalValue};
 use asmgen::func::FunctionInfo;
+use intrinsic::Intrinsic;
 use koopa::ir::entities::ValueData;
 use koopa::ir::{values::*, TypeKind};
 use koopa::ir::{BasicBlock, BinaryOp, Function, FunctionData, Program, Value, ValueKind};
-use std::fs::File;
 use std::io::{Result, Write};
 /// Trait for generating RISC-V assembly.
 pub trait GenerateAsm<'p, 'i> {
     type Out;
 
-    fn generate(&self, file: &mut File, program_info: &'i mut ProgramInfo<'p>)
+    fn generate(&self, file: &mut dyn Write, program_info: &'i mut ProgramInfo<'p>)
         -> Result<Self::Out>;
 }
 
@@ -23,7 +24,7 @@ trait GenerateValueAsm<'p, 'i> {
 
     fn generate(
         &self,
-        f: &mut File,
+        f: &mut dyn Write,
         info: &'i mut ProgramInfo<'p>,
         v: &ValueData,
     ) -> Result<Self::Out>;
@@ -32,7 +33,7 @@ trait GenerateValueAsm<'p, 'i> {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Program {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
         // generate global allocations
         for &value in self.inst_layout() {
             let data = self.borrow_value(value);
@@ -55,7 +56,7 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Program {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Function {
     type Out = &'p str;
 
-    fn generate(&self, _: &mut File, info: &mut ProgramInfo<'p>) -> Result<Self::Out> {
+    fn generate(&self, _: &mut dyn Write, info: &mut ProgramInfo<'p>) -> Result<Self::Out> {
         Ok(&info.program().func(*self).name()[1..])
     }
 }
@@ -63,31 +64,55 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Function {
 impl<'p, 'i> GenerateAsm<'p, 'i> for FunctionData {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
         // skip declarations
         if self.layout().entry_bb().is_none() {
             return Ok(());
         }
         // allocation stack slots and log argument number
+        let program = info.program();
         let func = asm_cur_func_mut!(info);
         for value in self.dfg().values().values() {
             // allocate stack slot
-            if value.kind().is_local_inst() && !value.used_by().is_empty() {
+            if let ValueKind::Alloc(_) = value.kind() {
                 func.alloc_slot(value);
             }
-            // log argument number
+            // log argument number, intrinsics are not real calls
             if let ValueKind::Call(call) = value.kind() {
-                func.log_arg_num(call.args().len());
+                if Intrinsic::from_func(program, call.callee()).is_none() {
+                    func.log_arg_num(call.args().len());
+                }
             }
         }
+        // allocate registers, spilled values are stored in stack slots
+        let alloc = regalloc::allocate(program, self);
+        for &value in self.dfg().values().keys() {
+            if let Some(&reg) = alloc.regs.get(&value) {
+                func.assign_reg(self.dfg().value(value), reg);
+            }
+        }
+        for &value in &alloc.spilled {
+            func.alloc_slot(self.dfg().value(value));
+        }
         // generate basic block names
         for (&bb, data) in self.dfg().bbs() {
-            // basic block parameters are not supported
-            assert!(data.params().is_empty());
             func.log_bb_name(bb, data.name());
         }
+        func.trace_layout(self);
         // generate prologue
         AsmBuilder::new(f, "t0").prologue(self.name(), func)?;
+        // move arguments passed by registers to their locations
+        let moves = self
+            .params()
+            .iter()
+            .take(8)
+            .enumerate()
+            .filter_map(|(i, &p)| {
+                let arg = func.local_value(self.dfg().value(p))?;
+                Some((arg, LocalValue::Reg(ARG_REGS[i])))
+            })
+            .collect();
+        parallel_move(f, moves)?;
         // generate instructions in basic blocks
         for (bb, node) in self.layout().bbs() {
             let name = bb.generate(f, info)?;
@@ -103,7 +128,7 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for FunctionData {
 impl<'p, 'i> GenerateAsm<'p, 'i> for BasicBlock {
     type Out = &'i str;
 
-    fn generate(&self, _: &mut File, info: &'i mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, _: &mut dyn Write, info: &'i mut ProgramInfo) -> Result<Self::Out> {
         Ok(asm_cur_func!(info).bb_name(*self))
     }
 }
@@ -111,8 +136,7 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for BasicBlock {
 impl<'p, 'i> GenerateAsm<'p, 'i> for ValueData {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
-        println!("{:#?}", self.kind());
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
         match self.kind() {
             ValueKind::Return(v) => v.generate(f, info),
             ValueKind::Alloc(_) => Ok(()),
@@ -136,51 +160,39 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for ValueData {
 impl<'p, 'i> GenerateValueAsm<'p, 'i> for GetPtr {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
-        let src = self.src().generate(f, info)?;
-        if src.is_ptr() {
-            src.write_to(f, "t0")?;
-        } else {
-            src.write_addr_to(f, "t0")?;
-        }
-        self.index().generate(f, info)?.write_to(f, "t1")?;
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
         let size = match v.ty().kind() {
             TypeKind::Pointer(base) => base.size(),
             _ => unreachable!(),
         };
-        let mut builder = AsmBuilder::new(f, "t2");
-        builder.muli("t1", "t1", size as i32)?;
-        builder.op2("add", "t0", "t0", "t1")?;
-        asm_value!(info, v).read_from(f, "t0", "t1")
+        let dest = asm_value!(info, v).dest_reg("t0");
+        let src = self.src().generate(f, info)?.reg(f, "t0")?;
+        let index = self.index().generate(f, info)?.into();
+        offset_ptr(f, dest, src, index, size)?;
+        asm_value!(info, v).read_from(f, dest, "t1")
     }
 }
 
 impl<'p, 'i> GenerateValueAsm<'p, 'i> for GetElemPtr {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
-        let src = self.src().generate(f, info)?;
-        if src.is_ptr() {
-            src.write_to(f, "t0")?;
-        } else {
-            src.write_addr_to(f, "t0")?;
-        }
-        self.index().generate(f, info)?.write_to(f, "t1")?;
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
         let size = match v.ty().kind() {
             TypeKind::Pointer(base) => base.size(),
             _ => unreachable!(),
         };
-        let mut builder = AsmBuilder::new(f, "t2");
-        builder.muli("t1", "t1", size as i32)?;
-        builder.op2("add", "t0", "t0", "t1")?;
-        asm_value!(info, v).read_from(f, "t0", "t1")
+        let dest = asm_value!(info, v).dest_reg("t0");
+        let src = self.src().generate(f, info)?.reg(f, "t0")?;
+        let index = self.index().generate(f, info)?.into();
+        offset_ptr(f, dest, src, index, size)?;
+        asm_value!(info, v).read_from(f, dest, "t1")
     }
 }
 
 impl<'p, 'i> GenerateAsm<'p, 'i> for Aggregate {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
         for &elem in self.elems() {
             info.program().borrow_value(elem).generate(f, info)?;
         }
@@ -191,7 +203,7 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Aggregate {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Integer {
     type Out = ();
 
-    fn generate(&self, f: &mut File, _: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, _: &mut ProgramInfo) -> Result<Self::Out> {
         writeln!(f, "  .word {}", self.value())
     }
 }
@@ -199,30 +211,37 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Integer {
 impl<'p, 'i> GenerateValueAsm<'p, 'i> for Call {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
+        if let Some(intrinsic) = Intrinsic::from_func(info.program(), self.callee()) {
+            return float_op(f, info, intrinsic, self.args(), v);
+        }
         let args = self
             .args()
             .iter()
             .map(|v| Ok(v.generate(f, info)?.into()))
             .collect::<Result<Vec<LocalValue>>>()?;
-        for (i, arg) in args.into_iter().enumerate() {
-            AsmValue::from(arg).write_to(f, "t0")?;
-            AsmValue::Arg(i).read_from(f, "t0", "t1")?;
+        // arguments after the 8th are passed on the stack
+        for (i, &arg) in args.iter().enumerate().skip(8) {
+            let reg = AsmValue::from(arg).reg(f, "t0")?;
+            AsmBuilder::new(f, "t1").sw(reg, "sp", ((i - 8) * 4) as i32)?;
         }
+        let moves = args
+            .into_iter()
+            .take(8)
+            .enumerate()
+            .map(|(i, arg)| (LocalValue::Reg(ARG_REGS[i]), arg))
+            .collect();
+        parallel_move(f, moves)?;
         let callee = self.callee().generate(f, info)?;
         AsmBuilder::new(f, "t0").call(callee)?;
-        if !v.used_by().is_empty() {
-            asm_value!(info, v).read_from(f, "a0", "t0")
-        } else {
-            Ok(())
-        }
+        asm_value!(info, v).read_from(f, "a0", "t0")
     }
 }
 
 impl<'p, 'i> GenerateValueAsm<'p, 'i> for ZeroInit {
     type Out = ();
 
-    fn generate(&self, f: &mut File, _: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, _: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
         writeln!(f, "  .zero {}", v.ty().size())
     }
 }
@@ -230,7 +249,7 @@ impl<'p, 'i> GenerateValueAsm<'p, 'i> for ZeroInit {
 impl<'p, 'i> GenerateAsm<'p, 'i> for GlobalAlloc {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
         info.program().borrow_value(self.init()).generate(f, info)
     }
 }
@@ -238,7 +257,7 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for GlobalAlloc {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Return {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
         if let Some(value) = self.value() {
             value.generate(f, info)?.write_to(f, "a0")?;
         }
@@ -249,7 +268,7 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Return {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Value {
     type Out = AsmValue<'i>;
 
-    fn generate(&self, _: &mut File, info: &'i mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, _: &mut dyn Write, info: &'i mut ProgramInfo) -> Result<Self::Out> {
         if self.is_global() {
             Ok(AsmValue::Global(info.value(*self)))
         } else {
@@ -257,8 +276,9 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Value {
             let value = info.program().func(func.func()).dfg().value(*self);
             Ok(match value.kind() {
                 ValueKind::Integer(i) => AsmValue::Const(i.value()),
-                ValueKind::FuncArgRef(i) => AsmValue::Arg(i.index()),
-                _ => AsmValue::from(func.slot_offset(value)),
+                // undefined values can be anything, use zero
+                ValueKind::Undef(_) => AsmValue::Const(0),
+                _ => AsmValue::from(func.local_value(value)),
             })
         }
     }
@@ -267,7 +287,8 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Value {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Jump {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
+        pass_args(f, info, self.target(), self.args())?;
         let label = self.target().generate(f, info)?;
         AsmBuilder::new(f, "t0").j(label)
     }
@@ -276,87 +297,179 @@ impl<'p, 'i> GenerateAsm<'p, 'i> for Jump {
 impl<'p, 'i> GenerateAsm<'p, 'i> for Store {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
-        let sp_offset = asm_cur_func!(info).sp_offset();
-        let value = self.value().generate(f, info)?;
-        if matches!(value, AsmValue::Arg(_)) {
-            value.write_arg_to(f, "t0", sp_offset)?;
-        } else {
-            value.write_to(f, "t0")?;
-        }
-        let dest = self.dest().generate(f, info)?;
-        if dest.is_ptr() {
-            dest.write_to(f, "t1")?;
-            AsmBuilder::new(f, "t2").sw("t0", "t1", 0)
-        } else {
-            dest.read_from(f, "t0", "t1")
-        }
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
+        let value = self.value().generate(f, info)?.reg(f, "t0")?;
+        self.dest().generate(f, info)?.store_from(f, value, "t1")
     }
 }
 
 impl<'p, 'i> GenerateValueAsm<'p, 'i> for Load {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
-        let src = self.src().generate(f, info)?;
-        src.write_to(f, "t0")?;
-        if src.is_ptr() {
-            AsmBuilder::new(f, "t1").lw("t0", "t0", 0)?;
-        }
-        asm_value!(info, v).read_from(f, "t0", "t1")
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
+        let dest = asm_value!(info, v).dest_reg("t0");
+        self.src().generate(f, info)?.load_to(f, dest)?;
+        asm_value!(info, v).read_from(f, dest, "t1")
     }
 }
 
 impl<'p, 'i> GenerateValueAsm<'p, 'i> for Binary {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
-        self.lhs().generate(f, info)?.write_to(f, "t0")?;
-        self.rhs().generate(f, info)?.write_to(f, "t1")?;
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo, v: &ValueData) -> Result<Self::Out> {
+        let lhs = self.lhs().generate(f, info)?.reg(f, "t0")?;
+        let rhs = self.rhs().generate(f, info)?.reg(f, "t1")?;
+        let dest = asm_value!(info, v).dest_reg("t0");
         let mut builder = AsmBuilder::new(f, "t2");
         match self.op() {
             BinaryOp::NotEq => {
-                builder.op2("xor", "t0", "t0", "t1")?;
-                builder.op1("snez", "t0", "t0")?;
+                builder.op2("xor", dest, lhs, rhs)?;
+                builder.op1("snez", dest, dest)?;
             }
             BinaryOp::Eq => {
-                builder.op2("xor", "t0", "t0", "t1")?;
-                builder.op1("seqz", "t0", "t0")?;
+                builder.op2("xor", dest, lhs, rhs)?;
+                builder.op1("seqz", dest, dest)?;
             }
-            BinaryOp::Gt => builder.op2("sgt", "t0", "t0", "t1")?,
-            BinaryOp::Lt => builder.op2("slt", "t0", "t0", "t1")?,
+            BinaryOp::Gt => builder.op2("sgt", dest, lhs, rhs)?,
+            BinaryOp::Lt => builder.op2("slt", dest, lhs, rhs)?,
             BinaryOp::Ge => {
-                builder.op2("slt", "t0", "t0", "t1")?;
-                builder.op1("seqz", "t0", "t0")?;
+                builder.op2("slt", dest, lhs, rhs)?;
+                builder.op1("seqz", dest, dest)?;
             }
             BinaryOp::Le => {
-                builder.op2("sgt", "t0", "t0", "t1")?;
-                builder.op1("seqz", "t0", "t0")?;
+                builder.op2("sgt", dest, lhs, rhs)?;
+                builder.op1("seqz", dest, dest)?;
             }
-            BinaryOp::Add => builder.op2("add", "t0", "t0", "t1")?,
-            BinaryOp::Sub => builder.op2("sub", "t0", "t0", "t1")?,
-            BinaryOp::Mul => builder.op2("mul", "t0", "t0", "t1")?,
-            BinaryOp::Div => builder.op2("div", "t0", "t0", "t1")?,
-            BinaryOp::Mod => builder.op2("rem", "t0", "t0", "t1")?,
-            BinaryOp::And => builder.op2("and", "t0", "t0", "t1")?,
-            BinaryOp::Or => builder.op2("or", "t0", "t0", "t1")?,
-            BinaryOp::Xor => builder.op2("xor", "t0", "t0", "t1")?,
-            BinaryOp::Shl => builder.op2("sll", "t0", "t0", "t1")?,
-            BinaryOp::Shr => builder.op2("srl", "t0", "t0", "t1")?,
-            BinaryOp::Sar => builder.op2("sra", "t0", "t0", "t1")?,
+            BinaryOp::Add => builder.op2("add", dest, lhs, rhs)?,
+            BinaryOp::Sub => builder.op2("sub", dest, lhs, rhs)?,
+            BinaryOp::Mul => builder.op2("mul", dest, lhs, rhs)?,
+            BinaryOp::Div => builder.op2("div", dest, lhs, rhs)?,
+            BinaryOp::Mod => builder.op2("rem", dest, lhs, rhs)?,
+            BinaryOp::And => builder.op2("and", dest, lhs, rhs)?,
+            BinaryOp::Or => builder.op2("or", dest, lhs, rhs)?,
+            BinaryOp::Xor => builder.op2("xor", dest, lhs, rhs)?,
+            BinaryOp::Shl => builder.op2("sll", dest, lhs, rhs)?,
+            BinaryOp::Shr => builder.op2("srl", dest, lhs, rhs)?,
+            BinaryOp::Sar => builder.op2("sra", dest, lhs, rhs)?,
         }
-        asm_value!(info, v).read_from(f, "t0", "t1")
+        asm_value!(info, v).read_from(f, dest, "t1")
     }
 }
 
 impl<'p, 'i> GenerateAsm<'p, 'i> for Branch {
     type Out = ();
 
-    fn generate(&self, f: &mut File, info: &mut ProgramInfo) -> Result<Self::Out> {
-        self.cond().generate(f, info)?.write_to(f, "t0")?;
-        let tlabel = self.true_bb().generate(f, info)?;
-        AsmBuilder::new(f, "t1").bnez("t0", tlabel)?;
+    fn generate(&self, f: &mut dyn Write, info: &mut ProgramInfo) -> Result<Self::Out> {
+        let cond = self.cond().generate(f, info)?.reg(f, "t0")?;
+        // arguments of the true target are passed in a separate edge
+        let tedge = if self.true_args().is_empty() {
+            None
+        } else {
+            Some(FunctionInfo::temp_label())
+        };
+        let tlabel = match &tedge {
+            Some(label) => label,
+            None => self.true_bb().generate(f, info)?,
+        };
+        AsmBuilder::new(f, "t1").bnez(cond, tlabel)?;
+        pass_args(f, info, self.false_bb(), self.false_args())?;
         let flabel = self.false_bb().generate(f, info)?;
-        AsmBuilder::new(f, "t1").j(flabel)
+        AsmBuilder::new(f, "t1").j(flabel)?;
+        if let Some(label) = tedge {
+            writeln!(f, "{label}:")?;
+            pass_args(f, info, self.true_bb(), self.true_args())?;
+            let tlabel = self.true_bb().generate(f, info)?;
+            AsmBuilder::new(f, "t1").j(tlabel)?;
+        }
+        Ok(())
+    }
+}
+
+/// Registers for passing arguments.
+const ARG_REGS: [&str; 8] = ["a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7"];
+
+/// Writes `src + index * size` to the `dest` register.
+fn offset_ptr(
+    f: &mut dyn Write,
+    dest: &'static str,
+    src: &'static str,
+    index: LocalValue,
+    size: usize,
+) -> Result<()> {
+    match index {
+        LocalValue::Const(index) => AsmBuilder::new(f, "t2").addi(dest, src, index * size as i32),
+        index => {
+            let index = AsmValue::from(index).reg(f, "t1")?;
+            let mut builder = AsmBuilder::new(f, "t2");
+            builder.muli("t1", index, size as i32)?;
+            builder.op2("add", dest, src, "t1")
+        }
+    }
+}
+
+/// Passes arguments to parameters of the given basic block.
+fn pass_args(f: &mut dyn Write, info: &mut ProgramInfo, bb: BasicBlock, args: &[Value]) -> Result<()> {
+    let func = asm_cur_func!(info);
+    let dfg = info.program().func(func.func()).dfg();
+    let params: Vec<_> = dfg
+        .bb(bb)
+        .params()
+        .iter()
+        .map(|&p| func.local_value(dfg.value(p)))
+        .collect();
+    let mut moves = Vec::new();
+    for (param, &arg) in params.into_iter().zip(args) {
+        if let Some(param) = param {
+            moves.push((param, arg.generate(f, info)?.into()));
+        }
+    }
+    parallel_move(f, moves)
+}
+
+/// Generates the float operation of the given intrinsic
+/// with F extension instructions.
+fn float_op(
+    f: &mut dyn Write,
+    info: &mut ProgramInfo,
+    intrinsic: Intrinsic,
+    args: &[Value],
+    v: &ValueData,
+) -> Result<()> {
+    // integer operand of conversion
+    if intrinsic == Intrinsic::IToF {
+        let src = args[0].generate(f, info)?.reg(f, "t0")?;
+        AsmBuilder::new(f, "t0").op1("fcvt.s.w", "ft0", src)?;
+        return asm_value!(info, v).read_from_freg(f, "ft0", "t0");
+    }
+    // float operands
+    for (&arg, (freg, temp)) in args.iter().zip([("ft0", "t0"), ("ft1", "t1")]) {
+        arg.generate(f, info)?.write_to_freg(f, freg, temp)?;
+    }
+    let op = match intrinsic {
+        Intrinsic::FAdd => "fadd.s",
+        Intrinsic::FSub => "fsub.s",
+        Intrinsic::FMul => "fmul.s",
+        Intrinsic::FDiv => "fdiv.s",
+        Intrinsic::FEq => "feq.s",
+        Intrinsic::FLt => "flt.s",
+        Intrinsic::FLe => "fle.s",
+        Intrinsic::FToI => {
+            let dest = asm_value!(info, v).dest_reg("t0");
+            writeln!(f, "  fcvt.w.s {dest}, ft0, rtz")?;
+            return asm_value!(info, v).read_from(f, dest, "t1");
+        }
+        Intrinsic::IToF => unreachable!(),
+    };
+    match intrinsic {
+        // comparisons produce integers
+        Intrinsic::FEq | Intrinsic::FLt | Intrinsic::FLe => {
+            let dest = asm_value!(info, v).dest_reg("t0");
+            AsmBuilder::new(f, "t1").op2(op, dest, "ft0", "ft1")?;
+            asm_value!(info, v).read_from(f, dest, "t1")
+        }
+        _ => {
+            AsmBuilder::new(f, "t0").op2(op, "ft0", "ft0", "ft1")?;
+            asm_value!(info, v).read_from_freg(f, "ft0", "t0")
+        }
     }
 }
@@ -5,15 +5,16 @@ mod generate;
 #[macro_use]
 mod values;
 mod builder;
+mod regalloc;
 
 use koopa::ir::{Program, Type};
-use std::fs::File;
-use std::io::Result;
+use std::io::{Result, Write};
 
 use self::generate::GenerateAsm;
 use self::info::ProgramInfo;
-/// from Koopa IR program to RISC-V assembly.
-pub fn generate_asm(program: &Program, path: &str) -> Result<()> {
+/// from Koopa IR program to RISC-V assembly, written to the given writer.
+pub fn generate_asm(program: &Program, out: &mut dyn Write) -> Result<()> {
     Type::set_ptr_size(4);
-    program.generate(&mut File::create(path)?, &mut ProgramInfo::new(program))
+    program.generate(out, &mut ProgramInfo::new(program))?;
+    out.flush()
 }
@@ -0,0 +1,224 @@
+use intrinsic::Intrinsic;
+use koopa::ir::{BasicBlock, FunctionData, Program, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
+
+/// Caller-saved registers available for allocation.
+/// `t0`-`t2` are reserved as temporaries of the instruction selector.
+/// Argument registers start from the 4th register.
+const CALLER_SAVED: &[&str] = &[
+  "t3", "t4", "t5", "t6", "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7",
+];
+
+/// Callee-saved registers available for allocation.
+pub const CALLEE_SAVED: &[&str] = &[
+  "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11",
+];
+
+/// Result of register allocation.
+pub struct RegAlloc {
+  /// Registers assigned to values.
+  pub regs: HashMap<Value, &'static str>,
+  /// Values that must live in stack slots.
+  pub spilled: Vec<Value>,
+}
+
+/// Live interval of a value, in terms of instruction positions.
+struct Interval {
+  value: Value,
+  start: usize,
+  end: usize,
+  /// `true` if there is a call instruction inside the interval.
+  cross_call: bool,
+  /// Preferred register, i.e. the argument register of a parameter.
+  hint: Option<&'static str>,
+}
+
+/// Allocates registers for all values that need one in the given function,
+/// by performing linear scan over live intervals.
+///
+/// Function parameters after the 8th are not allocated,
+/// they stay in the stack frame of the caller.
+pub fn allocate(program: &Program, data: &FunctionData) -> RegAlloc {
+  let intervals = live_intervals(program, data);
+  let mut regs = HashMap::new();
+  let mut spilled = Vec::new();
+  // active intervals, with their assigned registers
+  let mut active: Vec<(usize, &'static str)> = Vec::new();
+  for (cur, interval) in intervals.iter().enumerate() {
+    // expire old intervals, an interval ends at its last use,
+    // so its register can be reused by the value defined there
+    active.retain(|&(i, _)| intervals[i].end > interval.start);
+    // find a free register
+    let pool: Vec<_> = if interval.cross_call {
+      CALLEE_SAVED.to_vec()
+    } else {
+      CALLER_SAVED.iter().chain(CALLEE_SAVED).copied().collect()
+    };
+    let is_free = |r: &&str| active.iter().all(|(_, reg)| reg != r);
+    let free = interval
+      .hint
+      .filter(|r| pool.contains(r) && is_free(r))
+      .or_else(|| pool.iter().copied().find(is_free));
+    if let Some(reg) = free {
+      active.push((cur, reg));
+      regs.insert(interval.value, reg);
+      continue;
+    }
+    // spill the interval that ends last
+    let victim = active
+      .iter()
+      .enumerate()
+      .filter(|(_, (_, reg))| pool.contains(reg))
+      .max_by_key(|(_, (i, _))| intervals[*i].end)
+      .map(|(pos, &(i, reg))| (pos, i, reg));
+    match victim {
+      Some((pos, i, reg)) if intervals[i].end > interval.end => {
+        let victim = intervals[i].value;
+        regs.remove(&victim);
+        spilled.push(victim);
+        active[pos] = (cur, reg);
+        regs.insert(interval.value, reg);
+      }
+      _ => spilled.push(interval.value),
+    }
+  }
+  RegAlloc { regs, spilled }
+}
+
+/// Returns `true` if the value needs a register or a stack slot to hold it.
+fn needs_location(data: &FunctionData, value: Value) -> bool {
+  let value = data.dfg().value(value);
+  match value.kind() {
+    ValueKind::FuncArgRef(arg) => arg.index() < 8,
+    ValueKind::BlockArgRef(_) => true,
+    ValueKind::Alloc(_) => false,
+    kind => kind.is_local_inst() && !value.ty().is_unit(),
+  }
+}
+
+/// Computes the live intervals of all values in the given function,
+/// sorted by their start points.
+///
+/// Intervals are approximated as the range from the first position to
+/// the last position the value is live at, ignoring the holes.
+fn live_intervals(program: &Program, data: &FunctionData) -> Vec<Interval> {
+  let dfg = data.dfg();
+  let bbs: Vec<BasicBlock> = data.layout().bbs().keys().copied().collect();
+  // number instructions, position 0 is the function entry,
+  // parameters of a basic block are defined at the position before its first instruction
+  let mut pos = 0;
+  let mut ranges = HashMap::new();
+  let mut calls = Vec::new();
+  let mut order: HashMap<Value, usize> = data
+    .params()
+    .iter()
+    .enumerate()
+    .map(|(i, &p)| (p, i))
+    .collect();
+  for (&bb, node) in data.layout().bbs() {
+    pos += 1;
+    let start = pos;
+    for &param in dfg.bb(bb).params() {
+      order.insert(param, order.len());
+    }
+    for &inst in node.insts().keys() {
+      pos += 1;
+      order.insert(inst, order.len());
+      // intrinsics are lowered to instructions that do not clobber registers
+      if let ValueKind::Call(call) = dfg.value(inst).kind() {
+        if Intrinsic::from_func(program, call.callee()).is_none() {
+          calls.push(pos);
+        }
+      }
+    }
+    ranges.insert(bb, (start, pos));
+  }
+  // compute uses, definitions and successors of basic blocks
+  let mut uses: HashMap<BasicBlock, HashSet<Value>> = HashMap::new();
+  let mut defs: HashMap<BasicBlock, HashSet<Value>> = HashMap::new();
+  let mut succs: HashMap<BasicBlock, Vec<BasicBlock>> = HashMap::new();
+  for (&bb, node) in data.layout().bbs() {
+    let (u, d) = (uses.entry(bb).or_default(), defs.entry(bb).or_default());
+    d.extend(dfg.bb(bb).params());
+    for &inst in node.insts().keys() {
+      let kind = dfg.value(inst).kind();
+      for v in kind.value_uses() {
+        if !v.is_global() && needs_location(data, v) && !d.contains(&v) {
+          u.insert(v);
+        }
+      }
+      d.insert(inst);
+      succs.entry(bb).or_default().extend(kind.bb_uses());
+    }
+  }
+  // solve live-in/live-out sets of basic blocks
+  let mut live_in: HashMap<BasicBlock, HashSet<Value>> = HashMap::new();
+  let mut live_out: HashMap<BasicBlock, HashSet<Value>> = HashMap::new();
+  let mut changed = true;
+  while changed {
+    changed = false;
+    for &bb in bbs.iter().rev() {
+      let out: HashSet<Value> = succs[&bb]
+        .iter()
+        .flat_map(|s| live_in.get(s).into_iter().flatten().copied())
+        .collect();
+      let mut inn: HashSet<Value> = out.difference(&defs[&bb]).copied().collect();
+      inn.extend(&uses[&bb]);
+      if live_in.get(&bb) != Some(&inn) {
+        live_in.insert(bb, inn);
+        changed = true;
+      }
+      live_out.insert(bb, out);
+    }
+  }
+  // build intervals
+  let mut points: HashMap<Value, (usize, usize)> = HashMap::new();
+  let mut extend = |v: Value, p: usize| {
+    let range = points.entry(v).or_insert((p, p));
+    range.0 = range.0.min(p);
+    range.1 = range.1.max(p);
+  };
+  for &param in data.params() {
+    if needs_location(data, param) && !dfg.value(param).used_by().is_empty() {
+      extend(param, 0);
+    }
+  }
+  for (&bb, node) in data.layout().bbs() {
+    let (start, end) = ranges[&bb];
+    live_in[&bb].iter().for_each(|&v| extend(v, start));
+    live_out[&bb].iter().for_each(|&v| extend(v, end));
+    for &param in dfg.bb(bb).params() {
+      if !dfg.value(param).used_by().is_empty() {
+        extend(param, start);
+      }
+    }
+    let mut pos = start;
+    for &inst in node.insts().keys() {
+      pos += 1;
+      for v in dfg.value(inst).kind().value_uses() {
+        if !v.is_global() && needs_location(data, v) {
+          extend(v, pos);
+        }
+      }
+      if needs_location(data, inst) && !dfg.value(inst).used_by().is_empty() {
+        extend(inst, pos);
+      }
+    }
+  }
+  let mut intervals: Vec<_> = points
+    .into_iter()
+    .map(|(value, (start, end))| Interval {
+      value,
+      start,
+      end,
+      cross_call: calls.iter().any(|&c| start < c && c < end),
+      hint: match dfg.value(value).kind() {
+        ValueKind::FuncArgRef(arg) => Some(CALLER_SAVED[arg.index() + 4]),
+        _ => None,
+      },
+    })
+    .collect();
+  // sort by start point, keep the order deterministic
+  intervals.sort_by_key(|i| (i.start, i.end, order[&i.value]));
+  intervals
+}
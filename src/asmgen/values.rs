@@ -1,12 +1,11 @@
 use super::{builder::AsmBuilder, func::Slot};
-use std::fs::File;
-use std::io::Result;
+use std::io::{Result, Write};
 
 pub enum AsmValue<'i> {
     Global(&'i str),
     Local(Slot),
+    Reg(&'static str),
     Const(i32),
-    Arg(usize),
     Void,
 }
 
@@ -14,80 +13,122 @@ pub enum AsmValue<'i> {
 #[macro_export]
 macro_rules! asm_value {
     ($info:expr, $v:expr) => {
-        AsmValue::from(asm_cur_func!($info).slot_offset($v))
+        AsmValue::from(asm_cur_func!($info).local_value($v))
     };
 }
 
 impl<'i> AsmValue<'i> {
-    /// Determine whether it is a pointer or not
-    pub fn is_ptr(&self) -> bool {
-        matches!(self, Self::Local(slot) if slot.is_ptr)
-    }
-
     /// Writes the assembly value to the given register.
-    pub fn write_to(&self, f: &mut File, reg: &'static str) -> Result<()> {
+    ///
+    /// For global and local allocations, the address will be written.
+    pub fn write_to(&self, f: &mut dyn Write, reg: &'static str) -> Result<()> {
         let mut builder = AsmBuilder::new(f, reg);
         match self {
-            Self::Global(symbol) => {
-                builder.la(reg, symbol)?;
-                builder.lw(reg, reg, 0)
-            }
+            Self::Global(symbol) => builder.la(reg, symbol),
+            Self::Local(slot) if slot.is_alloc => builder.addi(reg, "sp", slot.offset as i32),
             Self::Local(slot) => builder.lw(reg, "sp", slot.offset as i32),
+            Self::Reg(r) => builder.mv(reg, r),
             Self::Const(num) => builder.li(reg, *num),
-            _ => unreachable!(),
+            Self::Void => unreachable!(),
         }
     }
 
-    /// Writes the assembly value (argument) to the given register.
-    pub fn write_arg_to(&self, f: &mut File, reg: &'static str, sp_offset: usize) -> Result<()> {
-        let mut builder = AsmBuilder::new(f, reg);
+    /// Returns a register that holds the assembly value, writes the value
+    /// to the given temporary register if it is not in a register.
+    pub fn reg(&self, f: &mut dyn Write, temp: &'static str) -> Result<&'static str> {
         match self {
-            Self::Arg(index) => {
-                if *index < 8 {
-                    builder.mv(reg, &format!("a{}", *index))
-                } else {
-                    builder.lw(reg, "sp", (sp_offset + (*index - 8) * 4) as i32)
-                }
+            Self::Reg(r) => Ok(r),
+            Self::Const(0) => Ok("x0"),
+            _ => {
+                self.write_to(f, temp)?;
+                Ok(temp)
             }
-            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the register that the result should be written to,
+    /// the given temporary register if the value is not in a register.
+    pub fn dest_reg(&self, temp: &'static str) -> &'static str {
+        match self {
+            Self::Reg(r) => r,
+            _ => temp,
         }
     }
 
     /// Reads the value of the given register to the assembly value.
-    pub fn read_from(&self, f: &mut File, reg: &'static str, temp: &'static str) -> Result<()> {
+    pub fn read_from(&self, f: &mut dyn Write, reg: &'static str, temp: &'static str) -> Result<()> {
         let mut builder = AsmBuilder::new(f, temp);
         match self {
-            Self::Global(symbol) => {
-                builder.la(temp, symbol)?;
-                builder.sw(reg, temp, 0)
+            Self::Local(slot) if !slot.is_alloc => builder.sw(reg, "sp", slot.offset as i32),
+            Self::Reg(r) => builder.mv(r, reg),
+            Self::Void => Ok(()),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Writes the assembly value (bit pattern of a float)
+    /// to the given float register.
+    pub fn write_to_freg(&self, f: &mut dyn Write, freg: &str, temp: &'static str) -> Result<()> {
+        match self {
+            Self::Local(slot) if !slot.is_alloc => {
+                AsmBuilder::new(f, temp).flw(freg, "sp", slot.offset as i32)
             }
-            Self::Local(slot) => builder.sw(reg, "sp", slot.offset as i32),
-            Self::Const(_) => unreachable!(),
-            Self::Arg(index) => {
-                if *index < 8 {
-                    builder.mv(&format!("a{}", *index), reg)
-                } else {
-                    builder.sw(reg, "sp", ((*index - 8) * 4) as i32)
-                }
+            _ => {
+                let reg = self.reg(f, temp)?;
+                AsmBuilder::new(f, temp).op1("fmv.w.x", freg, reg)
             }
-            Self::Void => Ok(()),
         }
     }
-    
-    pub fn write_addr_to(&self, f: &mut File, reg: &'static str) -> Result<()> {
-        let mut builder = AsmBuilder::new(f, reg);
+
+    /// Reads the value of the given float register to the assembly value.
+    pub fn read_from_freg(&self, f: &mut dyn Write, freg: &str, temp: &'static str) -> Result<()> {
+        let mut builder = AsmBuilder::new(f, temp);
         match self {
-            Self::Global(symbol) => builder.la(reg, symbol),
-            Self::Local(slot) => builder.addi(reg, "sp", slot.offset as i32),
+            Self::Local(slot) if !slot.is_alloc => builder.fsw(freg, "sp", slot.offset as i32),
+            Self::Reg(r) => builder.op1("fmv.x.w", r, freg),
+            Self::Void => Ok(()),
             _ => unreachable!(),
         }
     }
+
+    /// Loads the value the assembly value (pointer) points to,
+    /// to the given register.
+    pub fn load_to(&self, f: &mut dyn Write, reg: &'static str) -> Result<()> {
+        match self {
+            Self::Global(symbol) => {
+                let mut builder = AsmBuilder::new(f, reg);
+                builder.la(reg, symbol)?;
+                builder.lw(reg, reg, 0)
+            }
+            Self::Local(slot) if slot.is_alloc => {
+                AsmBuilder::new(f, reg).lw(reg, "sp", slot.offset as i32)
+            }
+            _ => {
+                let ptr = self.reg(f, reg)?;
+                AsmBuilder::new(f, reg).lw(reg, ptr, 0)
+            }
+        }
+    }
+
+    /// Stores the value of the given register to the place
+    /// the assembly value (pointer) points to.
+    pub fn store_from(&self, f: &mut dyn Write, reg: &'static str, temp: &'static str) -> Result<()> {
+        match self {
+            Self::Local(slot) if slot.is_alloc => {
+                AsmBuilder::new(f, temp).sw(reg, "sp", slot.offset as i32)
+            }
+            _ => {
+                let ptr = self.reg(f, temp)?;
+                AsmBuilder::new(f, temp).sw(reg, ptr, 0)
+            }
+        }
+    }
 }
 
-impl<'i> From<Option<Slot>> for AsmValue<'i> {
-    fn from(v: Option<Slot>) -> Self {
+impl<'i> From<Option<LocalValue>> for AsmValue<'i> {
+    fn from(v: Option<LocalValue>) -> Self {
         match v {
-            Some(slot) => Self::Local(slot),
+            Some(v) => v.into(),
             None => Self::Void,
         }
     }
@@ -97,13 +138,16 @@ impl<'i> From<LocalValue> for AsmValue<'i> {
     fn from(v: LocalValue) -> Self {
         match v {
             LocalValue::Local(slot) => Self::Local(slot),
+            LocalValue::Reg(reg) => Self::Reg(reg),
             LocalValue::Const(num) => Self::Const(num),
         }
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum LocalValue {
     Local(Slot),
+    Reg(&'static str),
     Const(i32),
 }
 
@@ -111,8 +155,49 @@ impl<'i> From<AsmValue<'i>> for LocalValue {
     fn from(value: AsmValue) -> Self {
         match value {
             AsmValue::Local(slot) => Self::Local(slot),
+            AsmValue::Reg(reg) => Self::Reg(reg),
             AsmValue::Const(num) => Self::Const(num),
             _ => unreachable!(),
         }
     }
 }
+
+/// Moves values between registers and stack slots as if all moves
+/// happen at the same time, `t0` is used to break cycles.
+///
+/// Each move is in the form of `(dest, src)`, destinations must be distinct.
+pub fn parallel_move(f: &mut dyn Write, mut moves: Vec<(LocalValue, LocalValue)>) -> Result<()> {
+    let reads = |src: &LocalValue, dest: &LocalValue| {
+        matches!((src, dest), (LocalValue::Reg(s), LocalValue::Reg(d)) if s == d)
+    };
+    moves.retain(|(dest, src)| !reads(src, dest));
+    while !moves.is_empty() {
+        // find a move whose destination is not read by other moves
+        let pos = moves
+            .iter()
+            .position(|(dest, _)| !moves.iter().any(|(_, src)| reads(src, dest)));
+        match pos {
+            Some(pos) => {
+                let (dest, src) = moves.remove(pos);
+                match dest {
+                    LocalValue::Reg(reg) => AsmValue::from(src).write_to(f, reg)?,
+                    _ => {
+                        let reg = AsmValue::from(src).reg(f, "t1")?;
+                        AsmValue::from(dest).read_from(f, reg, "t2")?;
+                    }
+                }
+            }
+            None => {
+                // all remaining moves form cycles, save the first destination
+                let dest = moves[0].0;
+                AsmValue::from(dest).write_to(f, "t0")?;
+                for (_, src) in &mut moves {
+                    if reads(src, &dest) {
+                        *src = LocalValue::Reg("t0");
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,33 +1,99 @@
+/// A range of bytes in the source file, `start` inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates a new span.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns a span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// Trait for AST nodes that know their position in the source file.
+pub trait Spanned {
+    fn span(&self) -> Span;
+}
+
 #[derive(Debug)]
 pub struct CompUnit {
     pub items: Vec<GlobalItem>,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub enum GlobalItem {
     Decl(Decl),
+    FuncDecl(FuncDecl),
     FuncDef(FuncDef),
 }
+/// Function declaration without body, e.g. `int f(int a[]);`.
+#[derive(Debug)]
+pub struct FuncDecl {
+    pub func_type: FuncType,
+    pub ident: String,
+    pub params: Vec<FuncFParam>,
+    pub span: Span,
+}
 #[derive(Debug)]
 pub struct FuncDef {
     pub func_type: FuncType,
     pub ident: String,
     pub params: Vec<FuncFParam>,
     pub block: Block,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub struct FuncFParam {
+    pub ty: BType,
     pub id: String,
     pub dims: Option<Vec<ConstExp>>,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FuncType {
     Void,
     Int,
+    Float,
+}
+
+impl FuncType {
+    /// Returns the base type of the return value, `None` if returns `void`.
+    pub fn base_type(self) -> Option<BType> {
+        match self {
+            Self::Void => None,
+            Self::Int => Some(BType::Int),
+            Self::Float => Some(BType::Float),
+        }
+    }
+}
+
+impl From<BType> for FuncType {
+    fn from(ty: BType) -> Self {
+        match ty {
+            BType::Int => Self::Int,
+            BType::Float => Self::Float,
+        }
+    }
+}
+
+/// Base type of variables, constants and parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BType {
+    Int,
+    Float,
 }
 #[derive(Debug)]
 pub struct Block {
     pub items: Vec<BlockItem>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -49,14 +115,19 @@ pub enum Stmt {
     Continue(Continue),
 }
 #[derive(Debug)]
-pub struct Break;
+pub struct Break {
+    pub span: Span,
+}
 #[derive(Debug)]
-pub struct Continue;
+pub struct Continue {
+    pub span: Span,
+}
 
 #[derive(Debug)]
 pub struct While {
     pub cond: Exp,
     pub body: Stmt,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -64,26 +135,31 @@ pub struct If {
     pub cond: Exp,
     pub then: Stmt,
     pub else_then: Option<Stmt>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct ExpStmt {
     pub exp: Option<Exp>,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub struct Assign {
     pub lval: LVal,
     pub exp: Exp,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Return {
     pub exp: Option<Exp>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
 pub struct Exp {
     pub lor: LOrExp,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub enum LAndExp {
@@ -111,22 +187,26 @@ pub enum LOrExp {
 pub enum UnaryExp {
     Primary(PrimaryExp),
     Call(FuncCall),
-    Unary(UnaryOp, Box<UnaryExp>),
+    Unary(UnaryOp, Box<UnaryExp>, Span),
 }
 
 #[derive(Debug)]
 pub struct FuncCall {
     pub id: String,
     pub args: Vec<Exp>,
-  }
+    pub span: Span,
+}
 
 #[derive(Debug)]
 pub enum PrimaryExp {
     Exp(Box<Exp>),
     LVal(LVal),
-    Number(i32),
+    /// Integer literal, which may be greater than `i32::MAX`.
+    Number(u32, Span),
+    /// Float literal, with its spelling in the source.
+    Float(f32, String, Span),
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
     Neg,
     LNot,
@@ -185,7 +265,9 @@ pub enum Decl {
 
 #[derive(Debug)]
 pub struct ConstDecl {
+    pub ty: BType,
     pub defs: Vec<ConstDef>,
+    pub span: Span,
 }
 
 #[derive(Debug)]
@@ -193,33 +275,192 @@ pub struct ConstDef {
     pub id: String,
     pub dims: Vec<ConstExp>,
     pub init: ConstInitVal,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub struct ConstExp {
     pub exp: Exp,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub enum ConstInitVal {
     Exp(ConstExp),
-    List(Vec<ConstInitVal>),
+    List(Vec<ConstInitVal>, Span),
 }
 #[derive(Debug)]
 pub struct VarDecl {
+    pub ty: BType,
     pub defs: Vec<VarDef>,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub struct VarDef {
     pub id: String,
     pub dims: Vec<ConstExp>,
     pub init: Option<InitVal>,
+    pub span: Span,
 }
 #[derive(Debug)]
 pub enum InitVal {
     Exp(Exp),
-    List(Vec<InitVal>),
+    List(Vec<InitVal>, Span),
 }
 #[derive(Debug)]
 pub struct LVal {
     pub id: String,
     pub indices: Vec<Exp>,
+    pub span: Span,
+}
+
+/// Implements `Spanned` for AST structs that store their own span.
+macro_rules! impl_spanned {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Spanned for $ty {
+                fn span(&self) -> Span {
+                    self.span
+                }
+            }
+        )*
+    };
+}
+
+impl_spanned!(
+    CompUnit, FuncDecl, FuncDef, FuncFParam, Block, Break, Continue, While, If, ExpStmt, Assign,
+    Return, Exp, FuncCall, ConstDecl, ConstDef, ConstExp, VarDecl, VarDef, LVal,
+);
+
+impl Spanned for GlobalItem {
+    fn span(&self) -> Span {
+        match self {
+            Self::Decl(decl) => decl.span(),
+            Self::FuncDecl(decl) => decl.span(),
+            Self::FuncDef(def) => def.span(),
+        }
+    }
+}
+
+impl Spanned for BlockItem {
+    fn span(&self) -> Span {
+        match self {
+            Self::Decl(decl) => decl.span(),
+            Self::Stmt(stmt) => stmt.span(),
+        }
+    }
+}
+
+impl Spanned for Stmt {
+    fn span(&self) -> Span {
+        match self {
+            Self::Assign(s) => s.span(),
+            Self::Return(s) => s.span(),
+            Self::ExpStmt(s) => s.span(),
+            Self::Block(s) => s.span(),
+            Self::If(s) => s.span(),
+            Self::While(s) => s.span(),
+            Self::Break(s) => s.span(),
+            Self::Continue(s) => s.span(),
+        }
+    }
+}
+
+impl Spanned for Decl {
+    fn span(&self) -> Span {
+        match self {
+            Self::Const(decl) => decl.span(),
+            Self::Var(decl) => decl.span(),
+        }
+    }
+}
+
+impl Spanned for ConstInitVal {
+    fn span(&self) -> Span {
+        match self {
+            Self::Exp(exp) => exp.span(),
+            Self::List(_, span) => *span,
+        }
+    }
+}
+
+impl Spanned for InitVal {
+    fn span(&self) -> Span {
+        match self {
+            Self::Exp(exp) => exp.span(),
+            Self::List(_, span) => *span,
+        }
+    }
+}
+
+impl Spanned for LOrExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::LAnd(exp) => exp.span(),
+            Self::LOrLAnd(lhs, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl Spanned for LAndExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Eq(exp) => exp.span(),
+            Self::LAndEq(lhs, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl Spanned for EqExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Rel(exp) => exp.span(),
+            Self::EqRel(lhs, _, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl Spanned for RelExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Add(exp) => exp.span(),
+            Self::RelAdd(lhs, _, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl Spanned for AddExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Mul(exp) => exp.span(),
+            Self::AddMul(lhs, _, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl Spanned for MulExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Unary(exp) => exp.span(),
+            Self::MulUnary(lhs, _, rhs) => lhs.span().to(rhs.span()),
+        }
+    }
+}
+
+impl Spanned for UnaryExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Primary(exp) => exp.span(),
+            Self::Call(call) => call.span(),
+            Self::Unary(_, _, span) => *span,
+        }
+    }
+}
+
+impl Spanned for PrimaryExp {
+    fn span(&self) -> Span {
+        match self {
+            Self::Exp(exp) => exp.span(),
+            Self::LVal(lval) => lval.span(),
+            Self::Number(_, span) | Self::Float(_, _, span) => *span,
+        }
+    }
 }
@@ -0,0 +1,103 @@
+extern crate compiler;
+
+use compiler::ast::Span;
+use compiler::diag::SourceFile;
+use std::env::args;
+use std::fs::{read_to_string, write};
+use std::io::{self, Read, Write};
+use std::iter::once;
+use std::process::exit;
+
+/// Default number of spaces of one indentation level.
+const DEFAULT_INDENT: usize = 4;
+
+const USAGE: &str = r#"Usage: sysy-fmt [--check] [--strip-comments] [--indent=N] [FILE...]
+
+Formats SysY source files in place, or formats the standard input to the
+standard output if no file or `-` is given. Comments are not preserved,
+so files with comments are not formatted in place by default.
+
+Options:
+  --check:          do not write, exit with 1 if any file is not formatted
+  --strip-comments: format files with comments in place, removing comments
+  --indent=N:       indent by N spaces, default to 4"#;
+
+fn main() {
+    match try_format() {
+        Ok(true) => {}
+        Ok(false) => exit(1),
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(-1);
+        }
+    }
+}
+
+/// Formats all input files, returns `false` if check failed.
+fn try_format() -> Result<bool, String> {
+    let mut check = false;
+    let mut strip = false;
+    let mut indent = DEFAULT_INDENT;
+    let mut files = Vec::new();
+    for arg in args().skip(1) {
+        if arg == "--check" {
+            check = true;
+        } else if arg == "--strip-comments" {
+            strip = true;
+        } else if let Some(n) = arg.strip_prefix("--indent=") {
+            indent = n.parse().map_err(|_| USAGE)?;
+        } else if arg.starts_with("--") {
+            return Err(USAGE.into());
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.is_empty() {
+        files.push("-".into());
+    }
+    let mut formatted = true;
+    for file in &files {
+        let text = if file == "-" {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text).map(|_| text)
+        } else {
+            read_to_string(file)
+        }
+        .map_err(|err| format!("{}: {}", file, err))?;
+        let name = if file == "-" { "<stdin>" } else { file };
+        let result = compiler::format(&text, indent)
+            .map_err(|err| err.render(&SourceFile::new(name, &text)))?;
+        if check {
+            if result != text {
+                println!("{} is not formatted", name);
+                formatted = false;
+            }
+        } else if file == "-" {
+            io::stdout()
+                .write_all(result.as_bytes())
+                .map_err(|err| err.to_string())?;
+        } else if result != text {
+            if !strip && has_comments(&text) {
+                return Err(format!(
+                    "{}: comments would be removed, use `--strip-comments` to format anyway",
+                    file
+                ));
+            }
+            write(file, result).map_err(|err| format!("{}: {}", file, err))?;
+        }
+    }
+    Ok(formatted)
+}
+
+/// Returns `true` if the source has comments, which are skipped by the lexer.
+fn has_comments(text: &str) -> bool {
+    let tokens = compiler::tokenize(text).unwrap_or_default();
+    let mut end = 0;
+    let eof = Span::new(text.len(), text.len());
+    tokens.iter().map(|t| t.span).chain(once(eof)).any(|span| {
+        // anything but whitespaces between tokens
+        let gap = &text[end..span.start];
+        end = span.end;
+        !gap.trim().is_empty()
+    })
+}
@@ -0,0 +1,118 @@
+use crate::ast::Span;
+use std::fmt;
+
+/// A source file, used for locating spans and rendering diagnostics.
+pub struct SourceFile<'s> {
+    name: &'s str,
+    text: &'s str,
+    /// Byte offsets of the beginning of every line.
+    lines: Vec<usize>,
+}
+
+impl<'s> SourceFile<'s> {
+    /// Creates a new source file.
+    pub fn new(name: &'s str, text: &'s str) -> Self {
+        let lines = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { name, text, lines }
+    }
+
+    /// Returns the 1-based line and column of the given byte offset.
+    pub fn line_col(&self, pos: usize) -> (usize, usize) {
+        let pos = pos.min(self.text.len());
+        let line = self.lines.partition_point(|&start| start <= pos) - 1;
+        let col = self.text[self.lines[line]..pos].chars().count();
+        (line + 1, col + 1)
+    }
+
+    /// Returns the text of the given 1-based line, without line terminator.
+    fn line_text(&self, line: usize) -> &'s str {
+        let start = self.lines[line - 1];
+        let end = self.lines.get(line).copied().unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    /// Creates a diagnostic pointing at the given span.
+    pub fn diagnostic<M>(&self, level: Level, span: Span, message: M) -> Diagnostic<'_, 's>
+    where
+        M: fmt::Display,
+    {
+        Diagnostic {
+            file: self,
+            level,
+            span,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+    /// Additional information attached to the previous diagnostic.
+    Note,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+            Self::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A diagnostic message with its location in the source file.
+///
+/// Rendered as `file:line:col: level: message`, followed by the source line
+/// and a caret line underlining the span.
+pub struct Diagnostic<'f, 's> {
+    file: &'f SourceFile<'s>,
+    level: Level,
+    span: Span,
+    message: String,
+}
+
+impl Diagnostic<'_, '_> {
+    /// Returns the severity of the diagnostic.
+    pub fn level(&self) -> Level {
+        self.level
+    }
+}
+
+impl fmt::Display for Diagnostic<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (line, col) = self.file.line_col(self.span.start);
+        writeln!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file.name, line, col, self.level, self.message
+        )?;
+        // print the first line of the span, underline the part it covers
+        let text = self.file.line_text(line);
+        let (end_line, end_col) = self.file.line_col(self.span.end);
+        let end_col = if end_line == line {
+            end_col
+        } else {
+            text.chars().count() + 1
+        };
+        let width = line.to_string().len();
+        let padding: String = text
+            .chars()
+            .take(col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{:width$} |", "")?;
+        writeln!(f, "{line} | {text}")?;
+        write!(
+            f,
+            "{:width$} | {padding}{}",
+            "",
+            "^".repeat(end_col.saturating_sub(col).max(1))
+        )
+    }
+}
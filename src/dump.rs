@@ -0,0 +1,546 @@
+use crate::ast::*;
+use crate::diag::SourceFile;
+use crate::parse::Lexeme;
+use std::fmt::{self, Write};
+
+/// A node of the serialized tree, rendered as an indented tree or JSON.
+///
+/// Expression precedence chains and parentheses are flattened, so binary
+/// and unary operations are the only inner nodes of expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// An AST node, with its kind, span and named fields.
+    Struct(&'static str, Span, Vec<(&'static str, Node)>),
+    List(Vec<Node>),
+    Str(String),
+    /// A keyword or an operator.
+    Sym(&'static str),
+    Int(i64),
+    Float(f32),
+    Null,
+}
+
+impl Node {
+    /// Renders the node as an indented tree, one node or field per line.
+    pub fn to_pretty(&self) -> String {
+        let mut out = String::new();
+        // writing to a string never fails
+        self.write_pretty(&mut out, "", 0).unwrap();
+        out
+    }
+
+    /// Renders the node as pretty-printed JSON.
+    ///
+    /// Structs are objects with their kind in `"kind"` and span in `"span"`
+    /// as `[start, end]` byte offsets, followed by their fields.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out, 0).unwrap();
+        out.push('\n');
+        out
+    }
+
+    /// Returns a copy of the node with all spans cleared,
+    /// for comparing ASTs of different sources.
+    pub fn without_spans(&self) -> Node {
+        match self {
+            Self::Struct(kind, _, fields) => Self::Struct(
+                kind,
+                Span::default(),
+                fields.iter().map(|(n, f)| (*n, f.without_spans())).collect(),
+            ),
+            Self::List(items) => Self::List(items.iter().map(Self::without_spans).collect()),
+            node => node.clone(),
+        }
+    }
+
+    /// Writes the node in tree form, `label` is the field name or list index.
+    fn write_pretty(&self, out: &mut String, label: &str, indent: usize) -> fmt::Result {
+        let sep = if label.is_empty() { "" } else { ": " };
+        write!(out, "{}{}{}", "  ".repeat(indent), label, sep)?;
+        match self {
+            Self::Struct(kind, span, fields) => {
+                writeln!(out, "{} {}..{}", kind, span.start, span.end)?;
+                for (name, field) in fields {
+                    field.write_pretty(out, name, indent + 1)?;
+                }
+                Ok(())
+            }
+            Self::List(items) if items.is_empty() => writeln!(out, "[]"),
+            Self::List(items) => {
+                // items follow in the next lines
+                out.truncate(out.trim_end().len());
+                writeln!(out)?;
+                for (i, item) in items.iter().enumerate() {
+                    item.write_pretty(out, &format!("[{}]", i), indent + 1)?;
+                }
+                Ok(())
+            }
+            Self::Str(s) => writeln!(out, "{:?}", s),
+            Self::Sym(s) => writeln!(out, "{}", s),
+            Self::Int(i) => writeln!(out, "{}", i),
+            Self::Float(f) => writeln!(out, "{:?}", f),
+            Self::Null => writeln!(out, "null"),
+        }
+    }
+
+    /// Writes the node in JSON form, nested lines are indented by `indent`.
+    fn write_json(&self, out: &mut String, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent + 1);
+        match self {
+            Self::Struct(kind, span, fields) => {
+                write!(out, "{{\n{}\"kind\": \"{}\",\n", pad, kind)?;
+                write!(out, "{}\"span\": [{}, {}]", pad, span.start, span.end)?;
+                for (name, field) in fields {
+                    write!(out, ",\n{}\"{}\": ", pad, name)?;
+                    field.write_json(out, indent + 1)?;
+                }
+                write!(out, "\n{}}}", "  ".repeat(indent))
+            }
+            Self::List(items) if items.is_empty() => write!(out, "[]"),
+            Self::List(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { "," };
+                    write!(out, "{}\n{}", sep, pad)?;
+                    item.write_json(out, indent + 1)?;
+                }
+                write!(out, "\n{}]", "  ".repeat(indent))
+            }
+            Self::Str(s) => write!(out, "{}", json_string(s)),
+            Self::Sym(s) => write!(out, "{}", json_string(s)),
+            Self::Int(i) => write!(out, "{}", i),
+            // JSON has no infinity or NaN
+            Self::Float(f) if !f.is_finite() => write!(out, "{}", json_string(&f.to_string())),
+            Self::Float(f) => write!(out, "{:?}", f),
+            Self::Null => write!(out, "null"),
+        }
+    }
+}
+
+/// Quotes and escapes the given string as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Converts the given tokens into a list of nodes, with their positions.
+pub fn tokens_to_node(tokens: &[Lexeme], source: &SourceFile) -> Node {
+    Node::List(
+        tokens
+            .iter()
+            .map(|token| {
+                let (line, col) = source.line_col(token.span.start);
+                Node::Struct(
+                    token.kind.name(),
+                    token.span,
+                    vec![
+                        ("text", Node::Str(token.text.into())),
+                        ("line", Node::Int(line as i64)),
+                        ("col", Node::Int(col as i64)),
+                    ],
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Trait for converting AST nodes into serialized tree nodes.
+pub trait ToNode {
+    fn to_node(&self) -> Node;
+}
+
+impl<T: ToNode> ToNode for Vec<T> {
+    fn to_node(&self) -> Node {
+        Node::List(self.iter().map(ToNode::to_node).collect())
+    }
+}
+
+impl<T: ToNode> ToNode for Option<T> {
+    fn to_node(&self) -> Node {
+        self.as_ref().map_or(Node::Null, ToNode::to_node)
+    }
+}
+
+impl<T: ToNode> ToNode for Box<T> {
+    fn to_node(&self) -> Node {
+        (**self).to_node()
+    }
+}
+
+impl ToNode for String {
+    fn to_node(&self) -> Node {
+        Node::Str(self.clone())
+    }
+}
+
+impl ToNode for BType {
+    fn to_node(&self) -> Node {
+        Node::Sym(match self {
+            Self::Int => "int",
+            Self::Float => "float",
+        })
+    }
+}
+
+impl ToNode for FuncType {
+    fn to_node(&self) -> Node {
+        Node::Sym(match self {
+            Self::Void => "void",
+            Self::Int => "int",
+            Self::Float => "float",
+        })
+    }
+}
+
+impl ToNode for CompUnit {
+    fn to_node(&self) -> Node {
+        Node::Struct("CompUnit", self.span, vec![("items", self.items.to_node())])
+    }
+}
+
+impl ToNode for GlobalItem {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Decl(decl) => decl.to_node(),
+            Self::FuncDecl(decl) => decl.to_node(),
+            Self::FuncDef(def) => def.to_node(),
+        }
+    }
+}
+
+impl ToNode for FuncDecl {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "FuncDecl",
+            self.span,
+            vec![
+                ("func_type", self.func_type.to_node()),
+                ("ident", self.ident.to_node()),
+                ("params", self.params.to_node()),
+            ],
+        )
+    }
+}
+
+impl ToNode for FuncDef {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "FuncDef",
+            self.span,
+            vec![
+                ("func_type", self.func_type.to_node()),
+                ("ident", self.ident.to_node()),
+                ("params", self.params.to_node()),
+                ("block", self.block.to_node()),
+            ],
+        )
+    }
+}
+
+impl ToNode for FuncFParam {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "FuncFParam",
+            self.span,
+            vec![
+                ("ty", self.ty.to_node()),
+                ("id", self.id.to_node()),
+                ("dims", self.dims.to_node()),
+            ],
+        )
+    }
+}
+
+impl ToNode for Block {
+    fn to_node(&self) -> Node {
+        Node::Struct("Block", self.span, vec![("items", self.items.to_node())])
+    }
+}
+
+impl ToNode for BlockItem {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Decl(decl) => decl.to_node(),
+            Self::Stmt(stmt) => stmt.to_node(),
+        }
+    }
+}
+
+impl ToNode for Stmt {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Assign(s) => Node::Struct(
+                "Assign",
+                s.span,
+                vec![("lval", s.lval.to_node()), ("exp", s.exp.to_node())],
+            ),
+            Self::Return(s) => Node::Struct("Return", s.span, vec![("exp", s.exp.to_node())]),
+            Self::ExpStmt(s) => Node::Struct("ExpStmt", s.span, vec![("exp", s.exp.to_node())]),
+            Self::Block(s) => s.to_node(),
+            Self::If(s) => Node::Struct(
+                "If",
+                s.span,
+                vec![
+                    ("cond", s.cond.to_node()),
+                    ("then", s.then.to_node()),
+                    ("else_then", s.else_then.to_node()),
+                ],
+            ),
+            Self::While(s) => Node::Struct(
+                "While",
+                s.span,
+                vec![("cond", s.cond.to_node()), ("body", s.body.to_node())],
+            ),
+            Self::Break(s) => Node::Struct("Break", s.span, vec![]),
+            Self::Continue(s) => Node::Struct("Continue", s.span, vec![]),
+        }
+    }
+}
+
+impl ToNode for Decl {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Const(decl) => Node::Struct(
+                "ConstDecl",
+                decl.span,
+                vec![("ty", decl.ty.to_node()), ("defs", decl.defs.to_node())],
+            ),
+            Self::Var(decl) => Node::Struct(
+                "VarDecl",
+                decl.span,
+                vec![("ty", decl.ty.to_node()), ("defs", decl.defs.to_node())],
+            ),
+        }
+    }
+}
+
+impl ToNode for ConstDef {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "ConstDef",
+            self.span,
+            vec![
+                ("id", self.id.to_node()),
+                ("dims", self.dims.to_node()),
+                ("init", self.init.to_node()),
+            ],
+        )
+    }
+}
+
+impl ToNode for ConstInitVal {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Exp(exp) => exp.to_node(),
+            Self::List(inits, span) => {
+                Node::Struct("InitList", *span, vec![("items", inits.to_node())])
+            }
+        }
+    }
+}
+
+impl ToNode for VarDef {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "VarDef",
+            self.span,
+            vec![
+                ("id", self.id.to_node()),
+                ("dims", self.dims.to_node()),
+                ("init", self.init.to_node()),
+            ],
+        )
+    }
+}
+
+impl ToNode for InitVal {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Exp(exp) => exp.to_node(),
+            Self::List(inits, span) => {
+                Node::Struct("InitList", *span, vec![("items", inits.to_node())])
+            }
+        }
+    }
+}
+
+impl ToNode for LVal {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "LVal",
+            self.span,
+            vec![
+                ("id", self.id.to_node()),
+                ("indices", self.indices.to_node()),
+            ],
+        )
+    }
+}
+
+impl ToNode for ConstExp {
+    fn to_node(&self) -> Node {
+        self.exp.to_node()
+    }
+}
+
+impl ToNode for Exp {
+    fn to_node(&self) -> Node {
+        self.lor.to_node()
+    }
+}
+
+/// Creates a binary operation node.
+fn binary<L, R>(op: &'static str, lhs: &L, rhs: &R) -> Node
+where
+    L: ToNode + Spanned,
+    R: ToNode + Spanned,
+{
+    Node::Struct(
+        "Binary",
+        lhs.span().to(rhs.span()),
+        vec![
+            ("op", Node::Sym(op)),
+            ("lhs", lhs.to_node()),
+            ("rhs", rhs.to_node()),
+        ],
+    )
+}
+
+impl ToNode for LOrExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::LAnd(exp) => exp.to_node(),
+            Self::LOrLAnd(lhs, rhs) => binary("||", &**lhs, rhs),
+        }
+    }
+}
+
+impl ToNode for LAndExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Eq(exp) => exp.to_node(),
+            Self::LAndEq(lhs, rhs) => binary("&&", &**lhs, rhs),
+        }
+    }
+}
+
+impl ToNode for EqExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Rel(exp) => exp.to_node(),
+            Self::EqRel(lhs, op, rhs) => {
+                let op = match op {
+                    EqOp::Eq => "==",
+                    EqOp::Neq => "!=",
+                };
+                binary(op, &**lhs, rhs)
+            }
+        }
+    }
+}
+
+impl ToNode for RelExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Add(exp) => exp.to_node(),
+            Self::RelAdd(lhs, op, rhs) => {
+                let op = match op {
+                    RelOp::Lt => "<",
+                    RelOp::Gt => ">",
+                    RelOp::Le => "<=",
+                    RelOp::Ge => ">=",
+                };
+                binary(op, &**lhs, rhs)
+            }
+        }
+    }
+}
+
+impl ToNode for AddExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Mul(exp) => exp.to_node(),
+            Self::AddMul(lhs, op, rhs) => {
+                let op = match op {
+                    AddOp::Add => "+",
+                    AddOp::Sub => "-",
+                };
+                binary(op, &**lhs, rhs)
+            }
+        }
+    }
+}
+
+impl ToNode for MulExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Unary(exp) => exp.to_node(),
+            Self::MulUnary(lhs, op, rhs) => {
+                let op = match op {
+                    MulOp::Mul => "*",
+                    MulOp::Div => "/",
+                    MulOp::Mod => "%",
+                };
+                binary(op, &**lhs, rhs)
+            }
+        }
+    }
+}
+
+impl ToNode for UnaryExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Primary(exp) => exp.to_node(),
+            Self::Call(call) => call.to_node(),
+            Self::Unary(op, exp, span) => {
+                let op = match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::LNot => "!",
+                };
+                Node::Struct(
+                    "Unary",
+                    *span,
+                    vec![("op", Node::Sym(op)), ("exp", exp.to_node())],
+                )
+            }
+        }
+    }
+}
+
+impl ToNode for FuncCall {
+    fn to_node(&self) -> Node {
+        Node::Struct(
+            "FuncCall",
+            self.span,
+            vec![("id", self.id.to_node()), ("args", self.args.to_node())],
+        )
+    }
+}
+
+impl ToNode for PrimaryExp {
+    fn to_node(&self) -> Node {
+        match self {
+            Self::Exp(exp) => exp.to_node(),
+            Self::LVal(lval) => lval.to_node(),
+            Self::Number(num, span) => {
+                Node::Struct("Number", *span, vec![("value", Node::Int((*num).into()))])
+            }
+            Self::Float(num, _, span) => {
+                Node::Struct("Float", *span, vec![("value", Node::Float(*num))])
+            }
+        }
+    }
+}
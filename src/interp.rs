@@ -0,0 +1,389 @@
+use intrinsic::{base_type, Intrinsic};
+use koopa::ir::{BasicBlock, Function, FunctionData, Program, Type};
+use koopa::ir::{Value, ValueKind};
+use opt::fold::eval_binary;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use sylib::{LibFunc, Runtime, Stats};
+
+/// Interprets the given Koopa IR program from `main`,
+/// reads the input of SysY runtime functions from `input`,
+/// and writes their output to `output`.
+///
+/// Memory is byte addressed with 32-bit pointers, integer operations
+/// follow RISC-V semantics, so results can be compared with the
+/// generated assembly.
+pub fn run<R: BufRead, W: Write>(program: &Program, input: R, output: W) -> Result<Stats> {
+    Type::set_ptr_size(4);
+    Interpreter::new(program, input, output).run()
+}
+
+/// Result type of interpreter.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error returned by interpreter.
+#[derive(Debug)]
+pub enum Error {
+    NoMain,
+    /// Call to a function declaration which is not in SysY runtime library.
+    UnknownFunction(String),
+    /// Out of bounds or misaligned memory access.
+    InvalidAccess(u32),
+    StackOverflow,
+    Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoMain => write!(f, "function `@main` not found"),
+            Self::UnknownFunction(name) => write!(f, "unknown function `{}`", name),
+            Self::InvalidAccess(addr) => write!(f, "invalid memory access at {:#010x}", addr),
+            Self::StackOverflow => write!(f, "stack overflow"),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+/// Address of the first global allocation, addresses below it are invalid.
+const GLOBAL_BASE: u32 = 0x1000;
+/// Size of the memory, the stack grows down from the end of it.
+const MEM_SIZE: u32 = 0x4000000;
+
+/// Stack space charged for each function call, bounds the depth of
+/// recursion like the frames of a real call stack.
+const FRAME_SIZE: u32 = 64;
+
+/// Interpreter of Koopa IR programs.
+struct Interpreter<'p, R, W> {
+    program: &'p Program,
+    /// Instructions of all basic blocks.
+    bbs: HashMap<BasicBlock, Vec<Value>>,
+    /// Addresses of global allocations.
+    globals: HashMap<Value, i32>,
+    mem: Vec<u8>,
+    /// End of global allocations.
+    data_end: u32,
+    sp: u32,
+    frames: Vec<Frame<'p>>,
+    runtime: Runtime<R, W>,
+    /// Number of executed instructions.
+    insts: u64,
+}
+
+/// Frame of a function call.
+struct Frame<'p> {
+    data: &'p FunctionData,
+    /// Values of parameters and instructions.
+    values: HashMap<Value, i32>,
+    bb: BasicBlock,
+    /// Index of the next instruction in the basic block.
+    next: usize,
+    /// Stack pointer before the call.
+    sp: u32,
+}
+
+impl<'p, R: BufRead, W: Write> Interpreter<'p, R, W> {
+    /// Creates a new interpreter, initializes global allocations.
+    fn new(program: &'p Program, input: R, output: W) -> Self {
+        let bbs = program
+            .funcs()
+            .values()
+            .flat_map(|data| data.layout().bbs())
+            .map(|(&bb, node)| (bb, node.insts().keys().copied().collect()))
+            .collect();
+        let mut interp = Self {
+            program,
+            bbs,
+            globals: HashMap::new(),
+            mem: vec![0; MEM_SIZE as usize],
+            data_end: GLOBAL_BASE,
+            sp: MEM_SIZE,
+            frames: Vec::new(),
+            runtime: Runtime::new(input, output),
+            insts: 0,
+        };
+        for &global in program.inst_layout() {
+            let data = program.borrow_value(global);
+            let init = match data.kind() {
+                ValueKind::GlobalAlloc(alloc) => alloc.init(),
+                _ => unreachable!(),
+            };
+            let addr = interp.data_end;
+            interp.data_end += (base_type(data.ty()).size() as u32 + 3) & !3;
+            interp.globals.insert(global, addr as i32);
+            interp.init(addr, init, &|v| {
+                let value = program.borrow_value(v);
+                (value.kind().clone(), value.ty().clone())
+            });
+        }
+        interp
+    }
+
+    /// Runs the program until `main` returns.
+    fn run(mut self) -> Result<Stats> {
+        let main = self
+            .program
+            .func_layout()
+            .iter()
+            .copied()
+            .find(|&f| {
+                let data = self.program.func(f);
+                data.name() == "@main" && data.layout().entry_bb().is_some()
+            })
+            .ok_or(Error::NoMain)?;
+        self.call(main, Vec::new())?;
+        let exit_code = loop {
+            let frame = self.frames.last_mut().unwrap();
+            let inst = self.bbs[&frame.bb][frame.next];
+            frame.next += 1;
+            self.insts += 1;
+            if let Some(ret) = self.exec(inst)? {
+                break ret & 0xff;
+            }
+        };
+        Ok(self.runtime.finish(exit_code, self.insts)?)
+    }
+
+    /// Executes an instruction, returns the return value of `main`
+    /// if the program exits.
+    fn exec(&mut self, inst: Value) -> Result<Option<i32>> {
+        let data = self.frames.last().unwrap().data;
+        let value = data.dfg().value(inst);
+        let result = match value.kind() {
+            ValueKind::Alloc(_) => {
+                let size = base_type(value.ty()).size() as u32;
+                let sp = self.push_stack(size)?;
+                self.mem[sp as usize..(sp + size) as usize].fill(0);
+                sp as i32
+            }
+            ValueKind::Load(load) => self.load(self.eval(load.src()))?,
+            ValueKind::Store(store) => {
+                let addr = self.eval(store.dest());
+                let src = store.value();
+                match data.dfg().value(src).kind() {
+                    ValueKind::Aggregate(_) | ValueKind::ZeroInit(_) => {
+                        self.check(addr, data.dfg().value(src).ty().size())?;
+                        self.init(addr as u32, src, &|v| {
+                            let value = data.dfg().value(v);
+                            (value.kind().clone(), value.ty().clone())
+                        });
+                    }
+                    _ => self.store(addr, self.eval(src))?,
+                }
+                return Ok(None);
+            }
+            ValueKind::GetPtr(gp) => {
+                let size = base_type(value.ty()).size() as i32;
+                let index = self.eval(gp.index());
+                self.eval(gp.src()).wrapping_add(index.wrapping_mul(size))
+            }
+            ValueKind::GetElemPtr(gep) => {
+                let size = base_type(value.ty()).size() as i32;
+                let index = self.eval(gep.index());
+                self.eval(gep.src()).wrapping_add(index.wrapping_mul(size))
+            }
+            ValueKind::Binary(bin) => eval_binary(bin.op(), self.eval(bin.lhs()), self.eval(bin.rhs())),
+            ValueKind::Branch(br) => {
+                if self.eval(br.cond()) != 0 {
+                    self.jump(br.true_bb(), br.true_args());
+                } else {
+                    self.jump(br.false_bb(), br.false_args());
+                }
+                return Ok(None);
+            }
+            ValueKind::Jump(jump) => {
+                self.jump(jump.target(), jump.args());
+                return Ok(None);
+            }
+            ValueKind::Call(call) => {
+                let args = call.args().iter().map(|&a| self.eval(a)).collect();
+                match self.call(call.callee(), args)? {
+                    Some(ret) => ret,
+                    // the value is set when the callee returns
+                    None => return Ok(None),
+                }
+            }
+            ValueKind::Return(ret) => {
+                let ret = ret.value().map(|v| self.eval(v));
+                let frame = self.frames.pop().unwrap();
+                self.sp = frame.sp;
+                let caller = match self.frames.last_mut() {
+                    Some(caller) => caller,
+                    None => return Ok(Some(ret.unwrap_or(0))),
+                };
+                // the caller is about to execute the instruction after the call
+                let call = self.bbs[&caller.bb][caller.next - 1];
+                if let Some(ret) = ret {
+                    caller.values.insert(call, ret);
+                }
+                return Ok(None);
+            }
+            _ => unreachable!(),
+        };
+        self.frames.last_mut().unwrap().values.insert(inst, result);
+        Ok(None)
+    }
+
+    /// Returns the value of the given value in the current function.
+    fn eval(&self, value: Value) -> i32 {
+        if value.is_global() {
+            return self.globals[&value];
+        }
+        let frame = self.frames.last().unwrap();
+        match frame.data.dfg().value(value).kind() {
+            ValueKind::Integer(int) => int.value(),
+            ValueKind::ZeroInit(_) | ValueKind::Undef(_) => 0,
+            _ => frame.values[&value],
+        }
+    }
+
+    /// Jumps to the given basic block, passes arguments to its parameters.
+    fn jump(&mut self, target: BasicBlock, args: &[Value]) {
+        let args: Vec<_> = args.iter().map(|&a| self.eval(a)).collect();
+        let frame = self.frames.last_mut().unwrap();
+        let params = frame.data.dfg().bb(target).params();
+        frame.values.extend(params.iter().copied().zip(args));
+        frame.bb = target;
+        frame.next = 0;
+    }
+
+    /// Calls the given function, returns the return value if it is
+    /// a declaration, which is evaluated immediately.
+    fn call(&mut self, func: Function, args: Vec<i32>) -> Result<Option<i32>> {
+        let data = self.program.func(func);
+        let entry = match data.layout().entry_bb() {
+            Some(entry) => entry,
+            None => return self.call_decl(func, &args).map(Some),
+        };
+        let sp = self.sp;
+        self.push_stack(FRAME_SIZE)?;
+        let values = data.params().iter().copied().zip(args).collect();
+        self.frames.push(Frame {
+            data,
+            values,
+            bb: entry,
+            next: 0,
+            sp,
+        });
+        Ok(None)
+    }
+
+    /// Allocates the given number of bytes on the stack,
+    /// returns the new stack pointer.
+    fn push_stack(&mut self, size: u32) -> Result<u32> {
+        let sp = self.sp.checked_sub(size).ok_or(Error::StackOverflow)? & !3;
+        if sp < self.data_end {
+            return Err(Error::StackOverflow);
+        }
+        self.sp = sp;
+        Ok(sp)
+    }
+
+    /// Calls a function declaration, which is either an intrinsic
+    /// or a function in SysY runtime library.
+    fn call_decl(&mut self, func: Function, args: &[i32]) -> Result<i32> {
+        if let Some(intrinsic) = Intrinsic::from_func(self.program, func) {
+            return Ok(intrinsic.eval(args));
+        }
+        let name = self.program.func(func).name();
+        let lib =
+            LibFunc::from_name(&name[1..]).ok_or_else(|| Error::UnknownFunction(name.into()))?;
+        let arg = |i: usize| args.get(i).copied().unwrap_or(0);
+        Ok(match lib {
+            LibFunc::GetInt => self.runtime.get_int()?,
+            LibFunc::GetCh => self.runtime.get_ch()?,
+            LibFunc::GetFloat => self.runtime.get_float()?.to_bits() as i32,
+            LibFunc::GetArray | LibFunc::GetFArray => {
+                let len = self.runtime.get_int()?;
+                for i in 0..len {
+                    let value = match lib {
+                        LibFunc::GetArray => self.runtime.get_int()?,
+                        _ => self.runtime.get_float()?.to_bits() as i32,
+                    };
+                    self.store(arg(0).wrapping_add(i.wrapping_mul(4)), value)?;
+                }
+                len
+            }
+            LibFunc::PutInt => self.runtime.put_int(arg(0)).map(|_| 0)?,
+            LibFunc::PutCh => self.runtime.put_ch(arg(0)).map(|_| 0)?,
+            LibFunc::PutFloat => {
+                let value = f32::from_bits(arg(0) as u32);
+                self.runtime.put_float(value).map(|_| 0)?
+            }
+            LibFunc::PutArray | LibFunc::PutFArray => {
+                let values = (0..arg(0).max(0))
+                    .map(|i| self.load(arg(1).wrapping_add(i.wrapping_mul(4))))
+                    .collect::<Result<Vec<_>>>()?;
+                if lib == LibFunc::PutArray {
+                    self.runtime.put_array(&values)?;
+                } else {
+                    let values: Vec<_> = values.iter().map(|&v| f32::from_bits(v as u32)).collect();
+                    self.runtime.put_farray(&values)?;
+                }
+                0
+            }
+            LibFunc::StartTime => {
+                self.runtime.start_time(self.insts);
+                0
+            }
+            LibFunc::StopTime => {
+                self.runtime.stop_time(self.insts);
+                0
+            }
+        })
+    }
+
+    /// Writes the given initializer to memory, `value` returns
+    /// the kind and the type of initializer elements.
+    fn init(&mut self, addr: u32, init: Value, value: &dyn Fn(Value) -> (ValueKind, Type)) {
+        let (kind, ty) = value(init);
+        let addr = addr as usize;
+        match kind {
+            ValueKind::Integer(int) => {
+                self.mem[addr..addr + 4].copy_from_slice(&int.value().to_le_bytes());
+            }
+            ValueKind::Aggregate(agg) => {
+                let mut addr = addr as u32;
+                for &elem in agg.elems() {
+                    self.init(addr, elem, value);
+                    addr += value(elem).1.size() as u32;
+                }
+            }
+            _ => self.mem[addr..addr + ty.size()].fill(0),
+        }
+    }
+
+    /// Checks the access of the given size at the given address.
+    fn check(&self, addr: i32, size: usize) -> Result<()> {
+        let addr = addr as u32;
+        let end = addr as u64 + size as u64;
+        if addr < GLOBAL_BASE || end > MEM_SIZE as u64 || !addr.is_multiple_of(4) {
+            return Err(Error::InvalidAccess(addr));
+        }
+        Ok(())
+    }
+
+    /// Loads a word from memory.
+    fn load(&self, addr: i32) -> Result<i32> {
+        self.check(addr, 4)?;
+        let addr = addr as usize;
+        let bytes = &self.mem[addr..addr + 4];
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Stores a word to memory.
+    fn store(&mut self, addr: i32, value: i32) -> Result<()> {
+        self.check(addr, 4)?;
+        let addr = addr as usize;
+        self.mem[addr..addr + 4].copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+}
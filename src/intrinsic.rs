@@ -0,0 +1,118 @@
+use koopa::ir::{Function, Program, Type, TypeKind};
+
+/// Canonical NaN produced by float operations.
+const CANONICAL_NAN: u32 = 0x7fc00000;
+
+/// Float operations in Koopa IR.
+///
+/// Koopa IR has no float type, so floats are stored as their bit patterns
+/// in `i32` values, and operations on them are expressed as calls to
+/// these intrinsic functions. The assembly generator lowers them
+/// to RISC-V F extension instructions instead of real calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Intrinsic {
+    FAdd,
+    FSub,
+    FMul,
+    FDiv,
+    /// `==` on floats, returns an integer.
+    FEq,
+    /// `<` on floats, returns an integer.
+    FLt,
+    /// `<=` on floats, returns an integer.
+    FLe,
+    /// Converts an integer to float.
+    IToF,
+    /// Converts a float to integer, rounding towards zero.
+    FToI,
+}
+
+impl Intrinsic {
+    pub const ALL: [Self; 9] = [
+        Self::FAdd,
+        Self::FSub,
+        Self::FMul,
+        Self::FDiv,
+        Self::FEq,
+        Self::FLt,
+        Self::FLe,
+        Self::IToF,
+        Self::FToI,
+    ];
+
+    /// Returns the name of the intrinsic function in Koopa IR.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FAdd => "@__sysy_fadd",
+            Self::FSub => "@__sysy_fsub",
+            Self::FMul => "@__sysy_fmul",
+            Self::FDiv => "@__sysy_fdiv",
+            Self::FEq => "@__sysy_feq",
+            Self::FLt => "@__sysy_flt",
+            Self::FLe => "@__sysy_fle",
+            Self::IToF => "@__sysy_itof",
+            Self::FToI => "@__sysy_ftoi",
+        }
+    }
+
+    /// Returns the number of parameters of the intrinsic function.
+    pub fn param_num(self) -> usize {
+        match self {
+            Self::IToF | Self::FToI => 1,
+            _ => 2,
+        }
+    }
+
+    /// Returns the intrinsic that the given function stands for.
+    pub fn from_func(program: &Program, func: Function) -> Option<Self> {
+        let data = program.func(func);
+        if data.layout().entry_bb().is_some() {
+            return None;
+        }
+        Self::ALL.iter().copied().find(|i| i.name() == data.name())
+    }
+
+    /// Evaluates the intrinsic on the given arguments, floats are bit patterns.
+    pub fn eval(self, args: &[i32]) -> i32 {
+        let float = |i: usize| f32::from_bits(args[i] as u32);
+        match self {
+            Self::FAdd => float_bits(float(0) + float(1)) as i32,
+            Self::FSub => float_bits(float(0) - float(1)) as i32,
+            Self::FMul => float_bits(float(0) * float(1)) as i32,
+            Self::FDiv => float_bits(float(0) / float(1)) as i32,
+            Self::FEq => (float(0) == float(1)) as i32,
+            Self::FLt => (float(0) < float(1)) as i32,
+            Self::FLe => (float(0) <= float(1)) as i32,
+            Self::IToF => (args[0] as f32).to_bits() as i32,
+            Self::FToI => float_to_int(float(0)),
+        }
+    }
+}
+
+/// Returns the bit pattern of the result of a float operation,
+/// NaN results are canonicalized as RISC-V does.
+pub fn float_bits(value: f32) -> u32 {
+    if value.is_nan() {
+        CANONICAL_NAN
+    } else {
+        value.to_bits()
+    }
+}
+
+/// Converts a float to integer, rounding towards zero.
+pub fn float_to_int(value: f32) -> i32 {
+    // conversion saturates, and NaN is converted to the maximum integer
+    if value.is_nan() {
+        i32::MAX
+    } else {
+        value as i32
+    }
+}
+
+/// Returns the base type of the given pointer type.
+pub fn base_type(ty: &Type) -> Type {
+    match ty.kind() {
+        TypeKind::Pointer(base) => base.clone(),
+        _ => unreachable!(),
+    }
+}
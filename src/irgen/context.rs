@@ -0,0 +1,107 @@
+use super::func::FunctionInfo;
+use crate::intrinsic::Intrinsic;
+use crate::sema::tree::{CompUnit, Symbol, SymbolId};
+use koopa::ir::{BasicBlock, Function, FunctionData, Program, Type, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Context of IR generation, including Koopa IR values and functions
+/// of all symbols, and information of the current function.
+pub struct Context<'t> {
+  unit: &'t CompUnit,
+  values: HashMap<SymbolId, Value>,
+  funcs: HashMap<SymbolId, Function>,
+  intrinsics: HashMap<Intrinsic, Function>,
+  used_intrinsics: HashSet<Intrinsic>,
+  pub cur_func: Option<FunctionInfo>,
+  pub loop_info: Vec<(BasicBlock, BasicBlock)>,
+}
+
+/// Returns a reference to the current function information.
+#[macro_export]
+macro_rules! cur_func {
+  ($ctx:expr) => {
+    $ctx.cur_func.as_ref().unwrap()
+  };
+}
+
+/// Returns a mutable reference to the current function information.
+/// #[macro_export]
+macro_rules! cur_func_mut {
+  ($ctx:expr) => {
+    $ctx.cur_func.as_mut().unwrap()
+  };
+}
+
+impl<'t> Context<'t> {
+  /// Creates a new `Context` for the given compile unit.
+  pub fn new(unit: &'t CompUnit) -> Self {
+    Self {
+      unit,
+      values: HashMap::new(),
+      funcs: HashMap::new(),
+      intrinsics: HashMap::new(),
+      used_intrinsics: HashSet::new(),
+      cur_func: None,
+      loop_info: Vec::new(),
+    }
+  }
+
+  /// Returns the symbol of the given id.
+  pub fn symbol(&self, id: SymbolId) -> &'t Symbol {
+    self.unit.symbol(id)
+  }
+
+  /// Returns `true` if is currently in global scope.
+  pub fn is_global(&self) -> bool {
+    self.cur_func.is_none()
+  }
+
+  /// Binds the Koopa IR value (pointer) to the given symbol.
+  pub fn new_value(&mut self, id: SymbolId, value: Value) {
+    trace!(Irgen, "bind `{}` to {:?}", self.symbol(id).name, value);
+    self.values.insert(id, value);
+  }
+
+  /// Returns the Koopa IR value of the given symbol.
+  pub fn value(&self, id: SymbolId) -> Value {
+    self.values[&id]
+  }
+
+  /// Binds the Koopa IR function to the given symbol.
+  pub fn new_func(&mut self, id: SymbolId, func: Function) {
+    trace!(Irgen, "bind function `{}` to {:?}", self.symbol(id).name, func);
+    self.funcs.insert(id, func);
+  }
+
+  /// Returns the Koopa IR function of the given symbol.
+  pub fn func(&self, id: SymbolId) -> Function {
+    self.funcs[&id]
+  }
+
+  /// Declares all intrinsic functions in the program, before functions
+  /// that may call them.
+  pub fn new_intrinsics(&mut self, program: &mut Program) {
+    for &intrinsic in &Intrinsic::ALL {
+      let params = vec![Type::get_i32(); intrinsic.param_num()];
+      let data = FunctionData::new_decl(intrinsic.name().into(), params, Type::get_i32());
+      self.intrinsics.insert(intrinsic, program.new_func(data));
+    }
+  }
+
+  /// Returns the given intrinsic function, and marks it as used.
+  pub fn intrinsic(&mut self, intrinsic: Intrinsic) -> Function {
+    self.used_intrinsics.insert(intrinsic);
+    self.intrinsics[&intrinsic]
+  }
+
+  /// Removes declarations of intrinsic functions which are never used.
+  pub fn remove_unused_intrinsics(&mut self, program: &mut Program) {
+    let used = &self.used_intrinsics;
+    self.intrinsics.retain(|intrinsic, func| {
+      if !used.contains(intrinsic) {
+        program.remove_func(*func);
+      }
+      used.contains(intrinsic)
+    });
+  }
+}
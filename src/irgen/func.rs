@@ -1,3 +1,4 @@
+use crate::ast::BType;
 use koopa::ir::Value;
 use koopa::ir::{builder::LocalBuilder, builder_traits::*};
 use koopa::ir::{BasicBlock, Function, Program, Type};
@@ -8,12 +9,17 @@ pub struct FunctionInfo {
   entry: BasicBlock,
   end: BasicBlock,
   cur: BasicBlock,
-  ret_val: Option<Value>,
+  ret_val: Option<(Value, BType)>,
 }
 
 impl FunctionInfo {
   /// Creates a new function information.
-  pub fn new(func: Function, entry: BasicBlock,end:BasicBlock,ret_val: Option<Value>) -> Self {
+  pub fn new(
+    func: Function,
+    entry: BasicBlock,
+    end: BasicBlock,
+    ret_val: Option<(Value, BType)>,
+  ) -> Self {
     Self {
       func,
       entry,
@@ -23,18 +29,13 @@ impl FunctionInfo {
     }
   }
 
-  /// Returns the curren function.
-  pub fn func(&self) -> Function {
-    self.func
-  }
-
   /// Returns the end basic block.
   pub fn end(&self) -> BasicBlock {
     self.end
   }
 
-  /// Returns the return value.
-  pub fn ret_val(&self) -> Option<Value> {
+  /// Returns the return value, with its type.
+  pub fn ret_val(&self) -> Option<(Value, BType)> {
     self.ret_val
   }
 
@@ -107,7 +108,7 @@ impl FunctionInfo {
     // push the end basic block
     self.push_bb(program, self.end);
     // generate return
-    let value = self.ret_val.map(|alloc| {
+    let value = self.ret_val.map(|(alloc, _)| {
       let value = self.new_value(program).load(alloc);
       self.push_inst(program, value);
       value
@@ -1,136 +1,63 @@
-use super::scopes:: Scopes;
-use super::{Error, Result};
+use super::context::Context;
+use crate::ast::BType;
+use crate::intrinsic::Intrinsic;
+use crate::sema::tree::{self, Const, Ty};
 use koopa::ir::builder_traits::*;
 use koopa::ir::Value as IrValue;
-use koopa::ir::{Program, Type, TypeKind};
-use std::iter::repeat_with;
+use koopa::ir::{BinaryOp, Program, Type};
 
-pub enum Value {
-    /// Koopa IR value.
-    Value(IrValue),
-    /// Constant integer.
-    Const(i32),
+/// Returns the Koopa IR type of the given type,
+/// floats are represented by integers.
+pub fn koopa_ty(ty: &Ty) -> Type {
+    match ty {
+        Ty::Int | Ty::Float => Type::get_i32(),
+        Ty::Void => Type::get_unit(),
+        Ty::Array(elem, len) => Type::get_array(koopa_ty(elem), *len),
+        Ty::Pointer(elem) => Type::get_pointer(koopa_ty(elem)),
+        Ty::Error => unreachable!(),
+    }
 }
+
+#[derive(Debug, Clone)]
 pub enum Initializer {
-  Const(i32),
+  Const(Const),
   Value(IrValue),
   List(Vec<Initializer>),
 }
 
 impl Initializer {
-  /// Reshapes the current initializer by using the given type.
-  /// Returns the reshaped initializer.
-  pub fn reshape(self, mut ty: &Type) -> Result<Self> {
-    // get length list
-    // array `int a[2][3][4]` yields [(4, 4), (3, 12), (2, 24)]
-    let mut lens = Vec::new();
-    loop {
-      match ty.kind() {
-        TypeKind::Int32 => break,
-        TypeKind::Array(base, len) => {
-          lens.push(*len);
-          ty = base;
-        }
-        _ => unreachable!(),
-      }
-    }
-    let mut last_len = 1;
-    let lens: Vec<_> = lens
-      .into_iter()
-      .rev()
-      .map(|l| {
-        last_len *= l;
-        (l, last_len)
-      })
-      .collect();
-    // perform reshape
+  /// Converts the initializer into a constant,
+  /// elements must be constants.
+  pub fn into_const(self, program: &mut Program, ctx: &Context) -> IrValue {
     match self {
-      v @ (Self::Const(_) | Self::Value(_)) if lens.is_empty() => Ok(v),
-      Self::List(l) if !lens.is_empty() => Self::reshape_impl(l, &lens),
-      _ => Err(Error::InvalidInit),
-    }
-  }
-
-  fn reshape_impl(inits: Vec<Self>, lens: &[(usize, usize)]) -> Result<Self> {
-    let mut reshaped: Vec<Vec<Self>> = repeat_with(Vec::new).take(lens.len() + 1).collect();
-    let mut len = 0;
-    // handle initializer elements
-    for init in inits {
-      // too many elements
-      if len >= lens.last().unwrap().1 {
-        return Err(Error::InvalidInit);
-      }
-      match init {
-        Self::List(list) => {
-          // get the next-level length list
-          let next_lens = match reshaped.iter().position(|v| !v.is_empty()) {
-            // not aligned
-            Some(0) => return Err(Error::InvalidInit),
-            Some(i) => &lens[..i],
-            None => &lens[..lens.len() - 1],
-          };
-          // reshape, and add to reshaped initializer list
-          reshaped[next_lens.len()].push(Self::reshape_impl(list, next_lens)?);
-          Self::carry(&mut reshaped, lens);
-          len += next_lens.last().unwrap().1;
-        }
-        _ => {
-          // just push
-          reshaped[0].push(init);
-          Self::carry(&mut reshaped, lens);
-          len += 1;
+      Self::Const(num) => {
+        if ctx.is_global() {
+          program.new_value().integer(num.to_bits())
+        } else {
+          cur_func!(ctx).new_value(program).integer(num.to_bits())
         }
       }
-    }
-    // fill zeros
-    while len < lens.last().unwrap().1 {
-      reshaped[0].push(Self::Const(0));
-      Self::carry(&mut reshaped, lens);
-      len += 1;
-    }
-    Ok(reshaped.pop().unwrap().pop().unwrap())
-  }
-
-  fn carry(reshaped: &mut [Vec<Self>], lens: &[(usize, usize)]) {
-    // perform carry
-    for (i, &(len, _)) in lens.iter().enumerate() {
-      if reshaped[i].len() == len {
-        let init = Self::List(reshaped[i].drain(..).collect());
-        reshaped[i + 1].push(init);
-      }
-    }
-  }
-
-  /// Converts the initializer (must be reshaped first) into a constant.
-  pub fn into_const(self, program: &mut Program, scopes: &Scopes) -> Result<IrValue> {
-    match self {
-      Self::Const(num) => Ok(if scopes.is_global() {
-        program.new_value().integer(num)
-      } else {
-        cur_func!(scopes).new_value(program).integer(num)
-      }),
-      Self::Value(_) => Err(Error::FailedToEval),
+      Self::Value(_) => unreachable!(),
       Self::List(list) => {
         let values = list
           .into_iter()
-          .map(|i| i.into_const(program, scopes))
-          .collect::<Result<_>>()?;
-        Ok(if scopes.is_global() {
+          .map(|i| i.into_const(program, ctx))
+          .collect();
+        if ctx.is_global() {
           program.new_value().aggregate(values)
         } else {
-          cur_func!(scopes).new_value(program).aggregate(values)
-        })
+          cur_func!(ctx).new_value(program).aggregate(values)
+        }
       }
     }
   }
 
-  /// Converts the initializer (must be reshaped first)
-  /// into store instructions.
-  pub fn into_stores(self, program: &mut Program, scopes: &Scopes, ptr: IrValue) {
-    let info = cur_func!(scopes);
+  /// Converts the initializer into store instructions.
+  pub fn into_stores(self, program: &mut Program, ctx: &Context, ptr: IrValue) {
+    let info = cur_func!(ctx);
     let store = match self {
       Self::Const(num) => {
-        let value = info.new_value(program).integer(num);
+        let value = info.new_value(program).integer(num.to_bits());
         info.new_value(program).store(value, ptr)
       }
       Self::Value(value) => info.new_value(program).store(value, ptr),
@@ -139,7 +66,7 @@ impl Initializer {
           let index = info.new_value(program).integer(i as i32);
           let ptr = info.new_value(program).get_elem_ptr(ptr, index);
           info.push_inst(program, ptr);
-          init.into_stores(program, scopes, ptr);
+          init.into_stores(program, ctx, ptr);
         }
         return;
       }
@@ -148,48 +75,98 @@ impl Initializer {
   }
 }
 
-/// An expression value.
-pub enum ExpValue {
-    /// An `void`.
-    Void,
-    /// An integer.
-    Int(IrValue),
-    /// An integer pointer.
-    IntPtr(IrValue),
-    /// An array pointer (part of array).
-    ArrPtr(IrValue),
+/// Converts the value from one type to another.
+pub fn convert(
+  program: &mut Program,
+  ctx: &mut Context,
+  val: IrValue,
+  from: BType,
+  to: BType,
+) -> IrValue {
+  match (from, to) {
+    (BType::Int, BType::Float) => call_intrinsic(program, ctx, Intrinsic::IToF, vec![val]),
+    (BType::Float, BType::Int) => call_intrinsic(program, ctx, Intrinsic::FToI, vec![val]),
+    _ => val,
   }
-  
-  impl ExpValue {
-    /// Converts the value into a right value.
-    pub fn into_val(self, program: &mut Program, scopes: &Scopes) -> Result<IrValue> {
-      match self {
-        Self::Void => Err(Error::UseVoidValue),
-        Self::Int(val) => Ok(val),
-        Self::IntPtr(ptr) => {
-          let info = cur_func!(scopes);
-          let load = info.new_value(program).load(ptr);
-          info.push_inst(program, load);
-          Ok(load)
-        }
-        Self::ArrPtr(ptr) => Ok(ptr),
-      }
-    }
-  
-    /// Converts the value into a integer right value.
-    pub fn into_int(self, program: &mut Program, scopes: &Scopes) -> Result<IrValue> {
-      match self {
-        Self::ArrPtr(_) => Err(Error::NonIntCalc),
-        _ => self.into_val(program, scopes),
-      }
-    }
-  
-    /// Converts the value into a left-value pointer.
-    pub fn into_ptr(self) -> Result<IrValue> {
-      match self {
-        Self::IntPtr(ptr) => Ok(ptr),
-        Self::ArrPtr(_) => Err(Error::ArrayAssign),
-        _ => unreachable!(),
-      }
+}
+
+/// Converts the value of the given type into a condition, which is
+/// an integer that is not zero if and only if the value is not zero.
+pub fn cond(program: &mut Program, ctx: &Context, val: IrValue, ty: BType) -> IrValue {
+  match ty {
+    BType::Int => val,
+    BType::Float => {
+      // clear the sign bit, so that `-0.0` is zero
+      let info = cur_func!(ctx);
+      let mask = info.new_value(program).integer(i32::MAX);
+      let cond = info.new_value(program).binary(BinaryOp::And, val, mask);
+      info.push_inst(program, cond);
+      cond
     }
-  }
\ No newline at end of file
+  }
+}
+
+/// Generates a binary operation on operands of the given type.
+pub fn binary(
+  program: &mut Program,
+  ctx: &mut Context,
+  op: tree::BinaryOp,
+  lhs: IrValue,
+  rhs: IrValue,
+  ty: BType,
+) -> IrValue {
+  let op = match op {
+    tree::BinaryOp::Add => BinaryOp::Add,
+    tree::BinaryOp::Sub => BinaryOp::Sub,
+    tree::BinaryOp::Mul => BinaryOp::Mul,
+    tree::BinaryOp::Div => BinaryOp::Div,
+    tree::BinaryOp::Mod => BinaryOp::Mod,
+    tree::BinaryOp::Eq => BinaryOp::Eq,
+    tree::BinaryOp::NotEq => BinaryOp::NotEq,
+    tree::BinaryOp::Lt => BinaryOp::Lt,
+    tree::BinaryOp::Gt => BinaryOp::Gt,
+    tree::BinaryOp::Le => BinaryOp::Le,
+    tree::BinaryOp::Ge => BinaryOp::Ge,
+  };
+  if ty == BType::Int {
+    let info = cur_func!(ctx);
+    let value = info.new_value(program).binary(op, lhs, rhs);
+    info.push_inst(program, value);
+    return value;
+  }
+  let (intrinsic, args) = match op {
+    BinaryOp::Add => (Intrinsic::FAdd, vec![lhs, rhs]),
+    BinaryOp::Sub => (Intrinsic::FSub, vec![lhs, rhs]),
+    BinaryOp::Mul => (Intrinsic::FMul, vec![lhs, rhs]),
+    BinaryOp::Div => (Intrinsic::FDiv, vec![lhs, rhs]),
+    BinaryOp::Eq | BinaryOp::NotEq => (Intrinsic::FEq, vec![lhs, rhs]),
+    BinaryOp::Lt => (Intrinsic::FLt, vec![lhs, rhs]),
+    BinaryOp::Gt => (Intrinsic::FLt, vec![rhs, lhs]),
+    BinaryOp::Le => (Intrinsic::FLe, vec![lhs, rhs]),
+    BinaryOp::Ge => (Intrinsic::FLe, vec![rhs, lhs]),
+    _ => unreachable!(),
+  };
+  let value = call_intrinsic(program, ctx, intrinsic, args);
+  if op == BinaryOp::NotEq {
+    let info = cur_func!(ctx);
+    let zero = info.new_value(program).integer(0);
+    let value = info.new_value(program).binary(BinaryOp::Eq, value, zero);
+    info.push_inst(program, value);
+    return value;
+  }
+  value
+}
+
+/// Generates a call to the given intrinsic function.
+fn call_intrinsic(
+  program: &mut Program,
+  ctx: &mut Context,
+  intrinsic: Intrinsic,
+  args: Vec<IrValue>,
+) -> IrValue {
+  let func = ctx.intrinsic(intrinsic);
+  let info = cur_func!(ctx);
+  let call = info.new_value(program).call(func, args);
+  info.push_inst(program, call);
+  call
+}
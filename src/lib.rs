@@ -0,0 +1,197 @@
+//! SysY compiler, compiles SysY source to Koopa IR and RISC-V assembly.
+//!
+//! The usual pipeline is [`parse`] → [`sema::analyze`] →
+//! [`irgen::generate_program`] (or [`compile`] for all three) →
+//! [`optimize`] → [`generate_asm`]. Generated programs can also be
+//! executed by the Koopa IR interpreter ([`interp`]) or the RISC-V
+//! simulator ([`sim`]). Internal information of each phase
+//! can be dumped by enabling [`trace`], and tokens and ASTs can be
+//! serialized by [`dump`], or printed back into source by [`pretty`].
+
+#[macro_use]
+pub mod trace;
+pub mod ast;
+pub mod diag;
+pub mod parse;
+pub mod dump;
+pub mod pretty;
+pub mod sema;
+#[macro_use]
+pub mod irgen;
+#[macro_use]
+pub mod asmgen;
+pub mod opt;
+mod intrinsic;
+pub mod interp;
+pub mod sim;
+pub mod sylib;
+extern crate koopa;
+extern crate lalrpop_util;
+
+use diag::{Level, SourceFile};
+use dump::ToNode;
+use koopa::front::Driver;
+use koopa::ir::Program;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Splits the given SysY source into tokens.
+pub fn tokenize(text: &str) -> Result<Vec<parse::Lexeme<'_>>> {
+    parse::tokenize(text).map_err(|err| Error::Parse(vec![err]))
+}
+
+/// Parses the given SysY source into a compile unit (ASTs).
+pub fn parse(text: &str) -> Result<ast::CompUnit> {
+    let comp_unit = parse::parse(text).map_err(Error::Parse)?;
+    trace!(Parse, "AST:\n{:#?}", comp_unit);
+    Ok(comp_unit)
+}
+
+/// Formats the given SysY source, indenting by the given number of spaces.
+///
+/// Returns [`Error::Format`] if the formatted source does not parse into
+/// the same AST, which is a bug of the pretty-printer.
+pub fn format(text: &str, indent: usize) -> Result<String> {
+    let comp_unit = parse(text)?;
+    let formatted = pretty::Printer::new(indent).print(&comp_unit);
+    let ast = comp_unit.to_node().without_spans();
+    match parse(&formatted) {
+        Ok(reparsed) if reparsed.to_node().without_spans() == ast => Ok(formatted),
+        _ => Err(Error::Format),
+    }
+}
+
+/// Compiles the given SysY source to Koopa IR program,
+/// returns the program and the warnings.
+pub fn compile(text: &str) -> Result<(Program, Vec<sema::Warning>)> {
+    let comp_unit = parse(text)?;
+    let (unit, warnings) = sema::analyze(&comp_unit).map_err(Error::Sema)?;
+    trace!(Sema, "typed AST:\n{:#?}", unit);
+    Ok((irgen::generate_program(&unit), warnings))
+}
+
+/// Parses the given Koopa IR text, errors are reported by Koopa frontend.
+pub fn parse_koopa(text: &str) -> Result<Program> {
+    Driver::from(text)
+        .generate_program()
+        .map_err(|_| Error::Koopa)
+}
+
+/// Parses the given Koopa IR file, errors are reported by Koopa frontend.
+pub fn parse_koopa_file<P: AsRef<Path> + Clone>(path: P) -> Result<Program> {
+    Driver::from_path(path)?
+        .generate_program()
+        .map_err(|_| Error::Koopa)
+}
+
+/// Runs the given optimization passes on the Koopa IR program, in order,
+/// returns the time spent in each pass.
+///
+/// Use [`opt::Pipeline`] directly to dump the Koopa IR after each pass.
+pub fn optimize<S: AsRef<str>>(program: &mut Program, passes: &[S]) -> Result<opt::Timings> {
+    let mut pipeline = opt::Pipeline::new(passes).map_err(Error::UnknownPass)?;
+    Ok(pipeline.run(program)?)
+}
+
+/// Generates RISC-V assembly for the Koopa IR program to the given writer.
+pub fn generate_asm<W: Write>(program: &Program, out: &mut W) -> Result<()> {
+    asmgen::generate_asm(program, out).map_err(Error::Io)
+}
+
+/// Result type of the compiler.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error returned by the compiler.
+#[derive(Debug)]
+pub enum Error {
+    /// All syntax errors found in the source.
+    Parse(Vec<parse::Error>),
+    /// All semantic errors found in the source, in the order of locations.
+    Sema(Vec<sema::Error>),
+    UnknownPass(String),
+    /// The formatted source does not parse into the same AST.
+    Format,
+    /// Errors in the input Koopa IR, which have been reported.
+    Koopa,
+    Io(io::Error),
+    Sim(sim::Error),
+    Interp(interp::Error),
+}
+
+impl Error {
+    /// Renders the error, with diagnostics pointing at the given source file.
+    pub fn render(&self, source: &SourceFile) -> String {
+        match self {
+            Self::Parse(errs) => {
+                let diags = errs.iter().map(|e| source.diagnostic(Level::Error, e.span, e));
+                render_all(diags, "syntax error")
+            }
+            Self::Sema(errs) => {
+                let diags = errs.iter().flat_map(|e| {
+                    let note = e.kind.note();
+                    let note = note.map(|(span, msg)| source.diagnostic(Level::Note, span, msg));
+                    std::iter::once(source.diagnostic(Level::Error, e.span, e)).chain(note)
+                });
+                render_all(diags, "error")
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Renders all diagnostics, followed by a summary line which counts
+/// all diagnostics but notes.
+fn render_all<'f, 's, I>(diags: I, what: &str) -> String
+where
+    I: Iterator<Item = diag::Diagnostic<'f, 's>>,
+    's: 'f,
+{
+    let mut text = String::new();
+    let mut count = 0;
+    for diag in diags {
+        text += &diag.to_string();
+        text.push('\n');
+        if diag.level() != Level::Note {
+            count += 1;
+        }
+    }
+    text + &summary(count, what)
+}
+
+/// Returns the last line of the rendered errors.
+fn summary(count: usize, what: &str) -> String {
+    let plural = if count > 1 { "s" } else { "" };
+    format!("aborting due to {} {}{}", count, what, plural)
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse(errs) => {
+                for err in errs {
+                    writeln!(f, "error: {}", err)?;
+                }
+                write!(f, "{}", summary(errs.len(), "syntax error"))
+            }
+            Self::Sema(errs) => {
+                for err in errs {
+                    writeln!(f, "error: {}", err)?;
+                }
+                write!(f, "{}", summary(errs.len(), "error"))
+            }
+            Self::UnknownPass(pass) => write!(f, "unknown optimization pass `{}`", pass),
+            Self::Format => write!(f, "formatted source parses into a different AST"),
+            Self::Koopa => write!(f, "aborting due to errors in Koopa IR input"),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::Sim(err) => write!(f, "simulator error: {}", err),
+            Self::Interp(err) => write!(f, "interpreter error: {}", err),
+        }
+    }
+}
@@ -1,61 +1,213 @@
-mod ast;
-#[macro_use]
-mod irgen;
-#[macro_use]
-mod asmgen;
+extern crate compiler;
 extern crate koopa;
-extern crate lalrpop_util;
 
+use compiler::diag::{Level, SourceFile};
+use compiler::dump::{self, ToNode};
+use compiler::sema::Lint;
+use compiler::trace::{self, Phase};
+use compiler::opt::{OptLevel, Pipeline};
+use compiler::{interp, sim};
 use koopa::back::KoopaGenerator;
-use koopa::ir::ValueKind;
-use lalrpop_util::lalrpop_mod;
 use std::env::args;
 use std::fmt;
 use std::fs::{read_to_string, File};
-use std::io::{self, Write};
+use std::io::{self, BufWriter, Read, Write};
 use std::process::exit;
 
-// 引用 lalrpop 生成的解析器
-// 因为我们刚刚创建了 sysy.lalrpop, 所以模块名是 sysy
-lalrpop_mod!(sysy);
-
 fn main() {
-    if let Err(err) = try_compile() {
-        eprintln!("{}", err);
-        exit(-1);
+    let result = try_compile();
+    // flush traces before exiting, errors on tracing are ignored
+    let _ = trace::disable();
+    match result {
+        Ok(code) => exit(code),
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(-1);
+        }
     }
 }
 
-fn try_compile() -> Result<(), Error> {
+/// Compiles the input file, returns the exit code.
+fn try_compile() -> Result<i32, Error> {
     //解析命令行参数
-    let (mode, input, output) = parse_args()?;
-
-    // 读取输入文件
-    let input = read_to_string(input).map_err(Error::File)?;
-
-    // 调用 lalrpop 生成的 parser 解析输入文件
-    let comp_unit = sysy::CompUnitParser::new().parse(&input).unwrap();
-    // 输出解析得到的 AST
-    // println!("{:#?}", comp_unit);
-    // println!("==================");
-    // generate IR
-    let program = irgen::generate_program(&comp_unit).map_err(Error::Generate)?;
-    if matches!(mode, Mode::Koopa) {
-        return KoopaGenerator::from_path(output.clone())
-            .map_err(Error::File)?
+    let Args {
+        mode,
+        input,
+        output,
+        from_koopa,
+        passes,
+        time_passes,
+        dump_ir,
+        trace,
+        trace_file,
+        json,
+        lints,
+        werror,
+    } = parse_args()?;
+    if !trace.is_empty() {
+        let sink: Box<dyn Write> = match trace_file {
+            Some(path) => Box::new(BufWriter::new(
+                File::create(path).map_err(compiler::Error::Io)?,
+            )),
+            None => Box::new(io::stderr()),
+        };
+        trace::enable(&trace, sink);
+    }
+    if matches!(mode, Mode::Ast | Mode::Tokens) {
+        dump_source(&mode, &input, &output, json)?;
+        return Ok(0);
+    }
+
+    let mut program = if from_koopa && input != "-" {
+        compiler::parse_koopa_file(&input).map_err(|err| match err {
+            compiler::Error::Io(err) => Error::File(err),
+            err => Error::Compile(err.to_string()),
+        })?
+    } else {
+        // 读取输入文件
+        let text = read_input(&input).map_err(Error::File)?;
+        let source = SourceFile::new(input_name(&input), &text);
+        let result = if from_koopa {
+            compiler::parse_koopa(&text).map(|program| (program, 0))
+        } else {
+            compiler::compile(&text).map(|(program, warnings)| {
+                let warnings: Vec<_> = warnings
+                    .iter()
+                    .filter(|w| lints.contains(&w.kind.lint()))
+                    .collect();
+                for warning in &warnings {
+                    eprintln!("{}", source.diagnostic(Level::Warning, warning.span, warning));
+                }
+                (program, warnings.len())
+            })
+        };
+        let (program, warnings) = result.map_err(|err| Error::Compile(err.render(&source)))?;
+        if werror && warnings > 0 {
+            let plural = if warnings > 1 { "s" } else { "" };
+            let message = format!("aborting due to {} warning{} (`-Werror`)", warnings, plural);
+            return Err(Error::Compile(message));
+        }
+        program
+    };
+    // run optimization passes
+    let mut pipeline = Pipeline::new(&passes).map_err(compiler::Error::UnknownPass)?;
+    if dump_ir {
+        pipeline = pipeline.dump_to(Box::new(BufWriter::new(io::stderr())));
+    }
+    let timings = pipeline.run(&mut program).map_err(compiler::Error::Io)?;
+    if time_passes {
+        eprintln!("{}", timings);
+    }
+    if matches!(mode, Mode::Koopa | Mode::Interp) {
+        let mut out = create_output(&output)?;
+        KoopaGenerator::new(&mut out)
             .generate_on(&program)
-            .map_err(Error::Io);
+            .map_err(compiler::Error::Io)?;
+        out.flush().map_err(compiler::Error::Io)?;
+        if matches!(mode, Mode::Koopa) {
+            return Ok(0);
+        }
+        // interpret the generated IR
+        let stdout = BufWriter::new(io::stdout().lock());
+        let stats =
+            interp::run(&program, io::stdin().lock(), stdout).map_err(compiler::Error::Interp)?;
+        eprintln!("{}", stats);
+        return Ok(stats.exit_code);
     }
     // generate RISC-V assembly
-    asmgen::generate_asm(&program, &output).map_err(Error::Io)
+    let mut asm = Vec::new();
+    compiler::generate_asm(&program, &mut asm)?;
+    let mut out = create_output(&output)?;
+    out.write_all(&asm).map_err(compiler::Error::Io)?;
+    out.flush().map_err(compiler::Error::Io)?;
+    if !matches!(mode, Mode::Run) {
+        return Ok(0);
+    }
+    // run the generated assembly
+    let asm = String::from_utf8(asm).expect("generated assembly is not UTF-8");
+    let stdout = BufWriter::new(io::stdout().lock());
+    let stats = sim::run(&asm, io::stdin().lock(), stdout).map_err(compiler::Error::Sim)?;
+    eprintln!("{}", stats);
+    Ok(stats.exit_code)
+}
+
+/// Writes the tokens or the AST of the input SysY source file to the output.
+fn dump_source(mode: &Mode, input: &str, output: &str, json: bool) -> Result<(), Error> {
+    let text = read_input(input).map_err(Error::File)?;
+    let source = SourceFile::new(input_name(input), &text);
+    let render = |err: compiler::Error| Error::Compile(err.render(&source));
+    let dumped = match mode {
+        Mode::Ast => {
+            let node = compiler::parse(&text).map_err(render)?.to_node();
+            if json {
+                node.to_json()
+            } else {
+                node.to_pretty()
+            }
+        }
+        _ => {
+            let tokens = compiler::tokenize(&text).map_err(render)?;
+            if json {
+                dump::tokens_to_node(&tokens, &source).to_json()
+            } else {
+                tokens
+                    .iter()
+                    .map(|token| {
+                        let (line, col) = source.line_col(token.span.start);
+                        format!("{}:{} {} {}\n", line, col, token.kind.name(), token.text)
+                    })
+                    .collect()
+            }
+        }
+    };
+    let mut out = create_output(output)?;
+    out.write_all(dumped.as_bytes()).map_err(compiler::Error::Io)?;
+    out.flush().map_err(compiler::Error::Io)?;
+    Ok(())
+}
+
+/// Returns the name of the input file used in diagnostics.
+fn input_name(path: &str) -> &str {
+    if path == "-" {
+        "<stdin>"
+    } else {
+        path
+    }
+}
+
+/// Reads the whole input file, or the standard input if the path is `-`.
+fn read_input(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        read_to_string(path)
+    }
+}
+
+/// Creates the output file, or writes to the standard output if the path is `-`.
+fn create_output(path: &str) -> Result<Box<dyn Write>, Error> {
+    Ok(if path == "-" {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        let file = File::create(path).map_err(compiler::Error::Io)?;
+        Box::new(BufWriter::new(file))
+    })
 }
 
 /// Error returned by `main` procedure.
 enum Error {
     InvalidArgs,
     File(io::Error),
-    Generate(irgen::Error),
-    Io(io::Error),
+    /// Rendered compiler error.
+    Compile(String),
+}
+
+impl From<compiler::Error> for Error {
+    fn from(err: compiler::Error) -> Self {
+        Self::Compile(err.to_string())
+    }
 }
 
 impl fmt::Display for Error {
@@ -63,16 +215,61 @@ impl fmt::Display for Error {
         match self {
             Self::InvalidArgs => write!(
                 f,
-                r#"Usage: kira MODE INPUT -o OUTPUT
+                r#"Usage: kira MODE INPUT -o OUTPUT [-OLEVEL | --passes=PASSES]
+            [-time-passes] [-dump-ir] [-from-koopa]
+            [-v] [--trace=PHASES] [--trace-file=FILE] [-json]
+            [-Wall] [-WLINT] [-Wno-LINT] [-Werror]
 
 Options:
-  MODE:   can be `-koopa`, `-riscv` or `-perf`
-  INPUT:  the input SysY source file
-  OUTPUT: the output file"#
+  MODE:   can be `-koopa`, `-riscv`, `-perf`, `-run`, `-interp`, `-ast`
+          or `-tokens`
+  INPUT:  the input SysY source file, or Koopa IR file if it ends with
+          `.koopa` or `-from-koopa` is given, `-` for the standard input
+  OUTPUT: the output file, `-` for the standard output
+  LEVEL:  optimization level, can be {}, default to `1` in
+          `-perf` mode and `0` in other modes,
+          `-O1` runs `{}`,
+          `-O2` runs `{}`
+  PASSES: comma-separated optimization passes to run instead of the ones
+          of the optimization level
+  -time-passes: report the time spent in each pass to the standard error
+  -dump-ir: dump the Koopa IR after each pass to the standard error
+  -v:     trace all phases, equivalent to `--trace={}`
+  PHASES: comma-separated phases to trace, can be `parse` (AST),
+          `sema` (definitions in scopes and the typed AST), `irgen`
+          (values bound to symbols), `opt` (passes) or `asmgen`
+          (stack slot layout, registers and labels)
+  FILE:   the file traces are written to, default to the standard error
+  -json:  dump in JSON instead of text in `-ast` and `-tokens` mode
+  LINT:   warnings to enable or disable, can be `overflow`, `unused`,
+          `unreachable`, `return`, `shadow` or `uninit`, all but `shadow`
+          are enabled by default, `-Wall` enables all of them
+  -Werror: abort if any warning is reported
+
+In `-ast` mode, the AST is dumped as an indented tree with byte ranges of
+nodes, and in `-tokens` mode, tokens are dumped one per line, with their
+line and column numbers.
+
+In `-run` mode, the generated assembly is also executed by the built-in
+RISC-V simulator, and in `-interp` mode, the generated Koopa IR is also
+executed by the built-in interpreter. Both use the standard input and
+output, the exit code and the number of executed instructions are
+reported to the standard error."#,
+                OptLevel::ALL
+                    .iter()
+                    .map(|l| format!("`{}`", l.name()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                OptLevel::O1.passes().join(","),
+                OptLevel::O2.passes().join(","),
+                Phase::ALL
+                    .iter()
+                    .map(|p| p.name())
+                    .collect::<Vec<_>>()
+                    .join(",")
             ),
-            Self::File(err) => write!(f, "invalid input SysY file: {}", err),
-            Self::Generate(err) => write!(f, "{}", err),
-            Self::Io(err) => write!(f, "I/O error: {}", err),
+            Self::File(err) => write!(f, "invalid input file: {}", err),
+            Self::Compile(err) => write!(f, "{}", err),
         }
     }
 }
@@ -82,21 +279,146 @@ enum Mode {
     Koopa,
     /// Compile SysY to RISC-V assembly.
     Riscv,
+    /// Compile SysY to RISC-V assembly, optimized by default.
+    Perf,
+    /// Compile SysY to RISC-V assembly, then run it in simulator.
+    Run,
+    /// Compile SysY to Koopa IR, then run it in interpreter.
+    Interp,
+    /// Parse SysY, then dump the AST.
+    Ast,
+    /// Split SysY into tokens, then dump them with their positions.
+    Tokens,
+}
+
+/// Command line arguments.
+struct Args {
+    mode: Mode,
+    input: String,
+    output: String,
+    /// Whether the input is Koopa IR instead of SysY.
+    from_koopa: bool,
+    /// Optimization passes to run, in order.
+    passes: Vec<String>,
+    /// Whether to report the time spent in each pass.
+    time_passes: bool,
+    /// Whether to dump the Koopa IR after each pass.
+    dump_ir: bool,
+    /// Phases to be traced.
+    trace: Vec<Phase>,
+    /// File to write traces to, `None` for the standard error.
+    trace_file: Option<String>,
+    /// Whether to dump tokens and AST in JSON.
+    json: bool,
+    /// Enabled lints.
+    lints: Vec<Lint>,
+    /// Whether to treat warnings as errors.
+    werror: bool,
 }
 
 /// Parses the arguments, returns `Error` if error occurred.
-fn parse_args() -> Result<(Mode, String, String), Error> {
+fn parse_args() -> Result<Args, Error> {
     let mut args = args();
     args.next();
-    match (args.next(), args.next(), args.next(), args.next()) {
-        (Some(m), Some(input), Some(o), Some(output)) if o == "-o" => {
-            let mode = match m.as_str() {
-                "-koopa" => Mode::Koopa,
-                "-riscv" => Mode::Riscv,
-                _ => return Err(Error::InvalidArgs),
-            };
-            Ok((mode, input, output))
+    let (m, input, output) = match (args.next(), args.next(), args.next(), args.next()) {
+        (Some(m), Some(input), Some(o), Some(output)) if o == "-o" => (m, input, output),
+        _ => return Err(Error::InvalidArgs),
+    };
+    // optional optimization, input type and tracing options
+    let mut level = None;
+    let mut passes = None;
+    let mut time_passes = false;
+    let mut dump_ir = false;
+    let mut from_koopa = input.ends_with(".koopa");
+    let mut trace = Vec::new();
+    let mut trace_file = None;
+    let mut json = false;
+    let mut lints: Vec<_> = Lint::ALL.iter().copied().filter(|l| l.is_default()).collect();
+    let mut werror = false;
+    for arg in args {
+        if arg == "-from-koopa" {
+            from_koopa = true;
+        } else if arg == "-time-passes" {
+            time_passes = true;
+        } else if arg == "-dump-ir" {
+            dump_ir = true;
+        } else if arg == "-json" {
+            json = true;
+        } else if arg == "-v" {
+            trace = Phase::ALL.to_vec();
+        } else if let Some(list) = arg.strip_prefix("--trace=") {
+            for name in list.split(',').filter(|p| !p.is_empty()) {
+                let phase = Phase::from_name(name).ok_or(Error::InvalidArgs)?;
+                if !trace.contains(&phase) {
+                    trace.push(phase);
+                }
+            }
+        } else if let Some(path) = arg.strip_prefix("--trace-file=") {
+            trace_file = Some(path.into());
+        } else if arg == "-Wall" {
+            lints = Lint::ALL.to_vec();
+        } else if arg == "-Werror" {
+            werror = true;
+        } else if let Some(name) = arg.strip_prefix("-Wno-") {
+            let lint = Lint::from_name(name).ok_or(Error::InvalidArgs)?;
+            lints.retain(|&l| l != lint);
+        } else if let Some(name) = arg.strip_prefix("-W") {
+            let lint = Lint::from_name(name).ok_or(Error::InvalidArgs)?;
+            if !lints.contains(&lint) {
+                lints.push(lint);
+            }
+        } else if let Some(name) = arg.strip_prefix("-O") {
+            level = Some(OptLevel::from_name(name).ok_or(Error::InvalidArgs)?);
+        } else {
+            let list = arg.strip_prefix("--passes=").ok_or(Error::InvalidArgs)?;
+            if passes.is_some() {
+                return Err(Error::InvalidArgs);
+            }
+            let list = list.split(',').filter(|p| !p.is_empty());
+            passes = Some(list.map(Into::into).collect());
         }
-        _ => Err(Error::InvalidArgs),
     }
+    let mode = match m.as_str() {
+        "-koopa" => Mode::Koopa,
+        "-riscv" => Mode::Riscv,
+        "-perf" => Mode::Perf,
+        "-run" => Mode::Run,
+        "-interp" => Mode::Interp,
+        "-ast" if !from_koopa => Mode::Ast,
+        "-tokens" if !from_koopa => Mode::Tokens,
+        _ => return Err(Error::InvalidArgs),
+    };
+    // JSON is only for dumping the source, which is never optimized
+    let dumps_source = matches!(mode, Mode::Ast | Mode::Tokens);
+    let optimizes = level.is_some() || passes.is_some() || time_passes || dump_ir;
+    if (json && !dumps_source) || (optimizes && dumps_source) {
+        return Err(Error::InvalidArgs);
+    }
+    let passes = match (level, passes) {
+        (Some(_), Some(_)) => return Err(Error::InvalidArgs),
+        (_, Some(passes)) => passes,
+        (level, None) => {
+            let default = if matches!(mode, Mode::Perf) {
+                OptLevel::O1
+            } else {
+                OptLevel::O0
+            };
+            let passes = level.unwrap_or(default).passes();
+            passes.iter().map(|p| p.to_string()).collect()
+        }
+    };
+    Ok(Args {
+        mode,
+        input,
+        output,
+        from_koopa,
+        passes,
+        time_passes,
+        dump_ir,
+        trace,
+        trace_file,
+        json,
+        lints,
+        werror,
+    })
 }
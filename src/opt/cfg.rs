@@ -0,0 +1,226 @@
+use super::rewrite::remove_values;
+use koopa::ir::{BasicBlock, FunctionData, Value, ValueKind};
+use std::collections::{HashMap, HashSet};
+
+/// Control flow graph of a function.
+pub struct Cfg {
+    preds: HashMap<BasicBlock, Vec<BasicBlock>>,
+    succs: HashMap<BasicBlock, Vec<BasicBlock>>,
+    /// Reachable basic blocks in reverse post-order.
+    rpo: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    /// Builds the control flow graph of the given function.
+    pub fn new(data: &FunctionData) -> Self {
+        let mut preds: HashMap<_, Vec<_>> = HashMap::new();
+        let mut succs: HashMap<_, Vec<_>> = HashMap::new();
+        for (&bb, node) in data.layout().bbs() {
+            preds.entry(bb).or_default();
+            let targets = succs.entry(bb).or_default();
+            if let Some(&inst) = node.insts().back_key() {
+                for succ in data.dfg().value(inst).kind().bb_uses() {
+                    if !targets.contains(&succ) {
+                        targets.push(succ);
+                        preds.entry(succ).or_default().push(bb);
+                    }
+                }
+            }
+        }
+        let mut cfg = Self {
+            preds,
+            succs,
+            rpo: Vec::new(),
+        };
+        if let Some(entry) = data.layout().entry_bb() {
+            let mut visited = HashSet::new();
+            cfg.post_order(entry, &mut visited);
+            cfg.rpo.reverse();
+        }
+        cfg
+    }
+
+    /// Appends basic blocks reachable from the given one to `rpo` in post-order.
+    fn post_order(&mut self, bb: BasicBlock, visited: &mut HashSet<BasicBlock>) {
+        // iterative DFS, avoids stack overflow on deeply nested code
+        visited.insert(bb);
+        let mut stack = vec![(bb, 0)];
+        while let Some((bb, i)) = stack.pop() {
+            match self.succs[&bb].get(i).copied() {
+                Some(succ) => {
+                    stack.push((bb, i + 1));
+                    if visited.insert(succ) {
+                        stack.push((succ, 0));
+                    }
+                }
+                None => self.rpo.push(bb),
+            }
+        }
+    }
+
+    /// Returns the predecessors of the given basic block.
+    pub fn preds(&self, bb: BasicBlock) -> &[BasicBlock] {
+        &self.preds[&bb]
+    }
+
+    /// Returns the successors of the given basic block.
+    pub fn succs(&self, bb: BasicBlock) -> &[BasicBlock] {
+        &self.succs[&bb]
+    }
+
+    /// Returns reachable basic blocks in reverse post-order.
+    pub fn rpo(&self) -> &[BasicBlock] {
+        &self.rpo
+    }
+}
+
+/// Dominator tree of a function.
+pub struct DomTree {
+    idoms: HashMap<BasicBlock, BasicBlock>,
+    children: HashMap<BasicBlock, Vec<BasicBlock>>,
+}
+
+impl DomTree {
+    /// Builds the dominator tree of reachable basic blocks, using the algorithm
+    /// by Cooper, Harvey and Kennedy.
+    pub fn new(cfg: &Cfg) -> Self {
+        let order: HashMap<_, _> = cfg
+            .rpo()
+            .iter()
+            .enumerate()
+            .map(|(i, &bb)| (bb, i))
+            .collect();
+        let mut idoms = HashMap::new();
+        if let Some(&entry) = cfg.rpo().first() {
+            idoms.insert(entry, entry);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &bb in cfg.rpo().iter().skip(1) {
+                let mut new_idom = None;
+                for &pred in cfg.preds(bb) {
+                    if !idoms.contains_key(&pred) {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(mut other) => {
+                            // find the common dominator of `pred` and `other`
+                            let mut pred = pred;
+                            while pred != other {
+                                while order[&pred] > order[&other] {
+                                    pred = idoms[&pred];
+                                }
+                                while order[&other] > order[&pred] {
+                                    other = idoms[&other];
+                                }
+                            }
+                            pred
+                        }
+                    });
+                }
+                let new_idom = new_idom.unwrap();
+                if idoms.insert(bb, new_idom) != Some(new_idom) {
+                    changed = true;
+                }
+            }
+        }
+        let mut children: HashMap<_, Vec<_>> = HashMap::new();
+        for &bb in cfg.rpo() {
+            children.entry(bb).or_default();
+            if let Some(&idom) = idoms.get(&bb).filter(|&&idom| idom != bb) {
+                children.entry(idom).or_default().push(bb);
+            }
+        }
+        Self { idoms, children }
+    }
+
+    /// Returns the immediate dominator of the given basic block,
+    /// `None` if the basic block is the entry.
+    pub fn idom(&self, bb: BasicBlock) -> Option<BasicBlock> {
+        self.idoms.get(&bb).copied().filter(|&idom| idom != bb)
+    }
+
+    /// Returns `true` if `a` dominates `b`, every basic block dominates itself.
+    pub fn dominates(&self, a: BasicBlock, mut b: BasicBlock) -> bool {
+        while a != b {
+            match self.idom(b) {
+                Some(idom) => b = idom,
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Returns the basic blocks immediately dominated by the given one.
+    pub fn children(&self, bb: BasicBlock) -> &[BasicBlock] {
+        &self.children[&bb]
+    }
+
+    /// Computes dominance frontiers of all reachable basic blocks.
+    pub fn frontiers(&self, cfg: &Cfg) -> HashMap<BasicBlock, HashSet<BasicBlock>> {
+        let mut frontiers: HashMap<_, HashSet<_>> = HashMap::new();
+        for &bb in cfg.rpo() {
+            frontiers.entry(bb).or_default();
+            let preds = cfg.preds(bb);
+            if preds.len() < 2 {
+                continue;
+            }
+            let idom = self.idom(bb);
+            for &pred in preds.iter().filter(|p| self.idoms.contains_key(p)) {
+                let mut runner = Some(pred);
+                while runner.is_some() && runner != idom {
+                    let r = runner.unwrap();
+                    frontiers.entry(r).or_default().insert(bb);
+                    runner = self.idom(r);
+                }
+            }
+        }
+        frontiers
+    }
+}
+
+/// Removes basic blocks that are unreachable from the entry,
+/// and all instructions in them.
+pub fn remove_unreachable_bbs(data: &mut FunctionData, cfg: &Cfg) {
+    let reachable: HashSet<_> = cfg.rpo().iter().copied().collect();
+    let bbs: Vec<_> = data
+        .layout()
+        .bbs()
+        .keys()
+        .copied()
+        .filter(|bb| !reachable.contains(bb))
+        .collect();
+    let mut insts = Vec::new();
+    for bb in &bbs {
+        let (_, node) = data.layout_mut().bbs_mut().remove(bb).unwrap();
+        insts.extend(node.insts().keys().copied());
+    }
+    // instructions in unreachable basic blocks are only used by each other
+    remove_values(data, insts);
+    for bb in bbs {
+        data.dfg_mut().remove_bb(bb);
+    }
+}
+
+/// Returns arguments passed to the given basic block by the terminator.
+pub fn incoming_args(
+    data: &FunctionData,
+    term: Value,
+    bb: BasicBlock,
+) -> impl Iterator<Item = &[Value]> {
+    let (targets, args): (Vec<_>, Vec<_>) = match data.dfg().value(term).kind() {
+        ValueKind::Jump(jump) => (vec![jump.target()], vec![jump.args()]),
+        ValueKind::Branch(br) => (
+            vec![br.true_bb(), br.false_bb()],
+            vec![br.true_args(), br.false_args()],
+        ),
+        _ => unreachable!(),
+    };
+    targets
+        .into_iter()
+        .zip(args)
+        .filter(move |(target, _)| *target == bb)
+        .map(|(_, args)| args)
+}
@@ -0,0 +1,123 @@
+use super::cfg::incoming_args;
+use super::rewrite::{rebuild_insts, remove_values};
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BasicBlock, Function, FunctionData, Value, ValueKind};
+use koopa::opt::FunctionPass;
+use std::collections::{HashMap, HashSet};
+
+/// Dead code elimination, removes instructions and basic block parameters
+/// whose results never affect stores, calls or control flow.
+///
+/// Instructions with side effects and terminators are live, and so are
+/// operands of live values. Arguments passed to a parameter are live only
+/// if the parameter is live, so unused loop-carried values are also removed.
+pub struct Dce;
+
+impl FunctionPass for Dce {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        let live = mark(data);
+        let dead: Vec<_> = data
+            .layout()
+            .bbs()
+            .nodes()
+            .flat_map(|node| node.insts().keys().copied())
+            .filter(|inst| !live.contains(inst))
+            .collect();
+        for &inst in &dead {
+            let bb = data.layout().parent_bb(inst).unwrap();
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        }
+        let params = remove_params(data, &live);
+        // dead values are only used by each other
+        remove_values(data, dead.into_iter().chain(params).collect());
+    }
+}
+
+/// Returns all live instructions and parameters.
+fn mark(data: &FunctionData) -> HashSet<Value> {
+    let dfg = data.dfg();
+    let mut params = HashMap::new();
+    let mut worklist = Vec::new();
+    for (&bb, node) in data.layout().bbs() {
+        for &param in dfg.bb(bb).params() {
+            params.insert(param, bb);
+        }
+        for &inst in node.insts().keys() {
+            match dfg.value(inst).kind() {
+                ValueKind::Store(_)
+                | ValueKind::Call(_)
+                | ValueKind::Jump(_)
+                | ValueKind::Branch(_)
+                | ValueKind::Return(_) => worklist.push(inst),
+                _ => {}
+            }
+        }
+    }
+    let mut live = HashSet::new();
+    while let Some(value) = worklist.pop() {
+        // skip global values, which are not in the function
+        if !dfg.values().contains_key(&value) || !live.insert(value) {
+            continue;
+        }
+        match dfg.value(value).kind() {
+            // arguments are live only if the parameters are live
+            ValueKind::Jump(_) => {}
+            ValueKind::Branch(br) => worklist.push(br.cond()),
+            ValueKind::BlockArgRef(arg) => {
+                let bb = params[&value];
+                for &user in dfg.bb(bb).used_by() {
+                    worklist.extend(incoming_args(data, user, bb).map(|args| args[arg.index()]));
+                }
+            }
+            kind => worklist.extend(kind.value_uses()),
+        }
+    }
+    live
+}
+
+/// Removes dead parameters from basic blocks and the corresponding
+/// arguments from terminators, returns the removed parameters.
+fn remove_params(data: &mut FunctionData, live: &HashSet<Value>) -> Vec<Value> {
+    // indices of live parameters of each basic block
+    let mut kept = HashMap::new();
+    let mut removed = Vec::new();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        let params = data.dfg().bb(bb).params().to_vec();
+        if params.iter().all(|p| live.contains(p)) {
+            continue;
+        }
+        let (alive, dead): (Vec<_>, Vec<_>) =
+            params.iter().enumerate().partition(|(_, p)| live.contains(p));
+        removed.extend(dead.into_iter().map(|(_, &p)| p));
+        kept.insert(bb, alive.iter().map(|&(i, _)| i).collect::<Vec<_>>());
+        // update indices of the remaining parameters
+        for (index, &(_, &param)) in alive.iter().enumerate() {
+            let mut value = data.dfg().value(param).clone();
+            match value.kind_mut() {
+                ValueKind::BlockArgRef(arg) => *arg.index_mut() = index,
+                _ => unreachable!(),
+            }
+            data.dfg_mut().replace_value_with(param).raw(value);
+        }
+        *data.dfg_mut().bb_mut(bb).params_mut() = alive.into_iter().map(|(_, &p)| p).collect();
+    }
+    if kept.is_empty() {
+        return removed;
+    }
+    // users of the updated parameters are logged again when rebuilt
+    let filter = |bb: BasicBlock, args: &mut Vec<Value>| {
+        if let Some(kept) = kept.get(&bb) {
+            *args = kept.iter().map(|&i| args[i]).collect();
+        }
+    };
+    rebuild_insts(data, |_, value| match value.kind_mut() {
+        ValueKind::Jump(jump) => filter(jump.target(), jump.args_mut()),
+        ValueKind::Branch(br) => {
+            filter(br.true_bb(), br.true_args_mut());
+            filter(br.false_bb(), br.false_args_mut());
+        }
+        _ => {}
+    });
+    removed
+}
@@ -0,0 +1,64 @@
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BasicBlock, BinaryOp, Function, FunctionData, Value, ValueKind};
+use koopa::opt::FunctionPass;
+
+/// Constant folding, replaces binary instructions whose operands
+/// are both integer constants with the computed constant.
+pub struct ConstFold;
+
+impl FunctionPass for ConstFold {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        // folding may turn other instructions into constant operations,
+        // repeat until nothing changes
+        while let Some((bb, inst, num)) = find_foldable(data) {
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+            data.dfg_mut().replace_value_with(inst).integer(num);
+        }
+    }
+}
+
+/// Finds the first foldable instruction in the function,
+/// returns its basic block, the instruction and the folded result.
+fn find_foldable(data: &FunctionData) -> Option<(BasicBlock, Value, i32)> {
+    let dfg = data.dfg();
+    let int = |v: Value| match dfg.value(v).kind() {
+        ValueKind::Integer(i) => Some(i.value()),
+        _ => None,
+    };
+    for (&bb, node) in data.layout().bbs() {
+        for &inst in node.insts().keys() {
+            if let ValueKind::Binary(bin) = dfg.value(inst).kind() {
+                if let (Some(lhs), Some(rhs)) = (int(bin.lhs()), int(bin.rhs())) {
+                    return Some((bb, inst, eval_binary(bin.op(), lhs, rhs)));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Evaluates the binary operation with the semantics of RV32IM,
+/// including wrapping on overflow and the results of division by zero.
+pub fn eval_binary(op: BinaryOp, lhs: i32, rhs: i32) -> i32 {
+    match op {
+        BinaryOp::NotEq => (lhs != rhs) as i32,
+        BinaryOp::Eq => (lhs == rhs) as i32,
+        BinaryOp::Gt => (lhs > rhs) as i32,
+        BinaryOp::Lt => (lhs < rhs) as i32,
+        BinaryOp::Ge => (lhs >= rhs) as i32,
+        BinaryOp::Le => (lhs <= rhs) as i32,
+        BinaryOp::Add => lhs.wrapping_add(rhs),
+        BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        BinaryOp::Mul => lhs.wrapping_mul(rhs),
+        BinaryOp::Div if rhs == 0 => -1,
+        BinaryOp::Div => lhs.wrapping_div(rhs),
+        BinaryOp::Mod if rhs == 0 => lhs,
+        BinaryOp::Mod => lhs.wrapping_rem(rhs),
+        BinaryOp::And => lhs & rhs,
+        BinaryOp::Or => lhs | rhs,
+        BinaryOp::Xor => lhs ^ rhs,
+        BinaryOp::Shl => lhs.wrapping_shl(rhs as u32),
+        BinaryOp::Shr => (lhs as u32).wrapping_shr(rhs as u32) as i32,
+        BinaryOp::Sar => lhs.wrapping_shr(rhs as u32),
+    }
+}
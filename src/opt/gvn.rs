@@ -0,0 +1,253 @@
+use super::cfg::{Cfg, DomTree};
+use super::rewrite::{map_operands, rebuild_insts, remove_values};
+use koopa::ir::{BasicBlock, BinaryOp, Function, FunctionData, Value, ValueKind};
+use koopa::opt::FunctionPass;
+use std::collections::HashMap;
+
+/// Global value numbering, removes instructions that compute the same
+/// value as another instruction that dominates them.
+///
+/// Binary operations and pointer calculations are numbered by walking the
+/// dominator tree. Loads are numbered in extended basic blocks, where
+/// stores only invalidate loads from pointers that may alias, and
+/// calls invalidate all loads.
+pub struct Gvn;
+
+impl FunctionPass for Gvn {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        let entry = data.layout().entry_bb().unwrap();
+        let cfg = Cfg::new(data);
+        let dom = DomTree::new(&cfg);
+        let mut numbering = Numbering {
+            data,
+            cfg: &cfg,
+            dom: &dom,
+            exprs: HashMap::new(),
+            leaders: HashMap::new(),
+        };
+        numbering.walk(entry);
+        let leaders = numbering.leaders;
+        if leaders.is_empty() {
+            return;
+        }
+        trace!(Opt, "gvn: {} redundant values", leaders.len());
+        for &inst in leaders.keys() {
+            let bb = data.layout().parent_bb(inst).unwrap();
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        }
+        rebuild_insts(data, |_, value| {
+            map_operands(value.kind_mut(), |v| leaders.get(&v).copied().unwrap_or(v))
+        });
+        // redundant values are only used by each other
+        remove_values(data, leaders.into_keys().collect());
+    }
+}
+
+/// Number of an operand, integers of the same value have the same number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Num {
+    Int(i32),
+    Value(Value),
+}
+
+/// Expression computed by a pure instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Expr {
+    Binary(BinaryOp, Num, Num),
+    GetPtr(Num, Num),
+    GetElemPtr(Num, Num),
+}
+
+/// Memory object that a pointer points into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Object {
+    /// Local allocation.
+    Alloc(Value),
+    /// Global allocation.
+    Global(Value),
+    /// Anything other than local allocations, e.g. array parameters.
+    Unknown,
+}
+
+/// Values loaded from or stored to pointers, by numbers of the pointers.
+type Memory = HashMap<Num, Value>;
+
+/// Step of walking the dominator tree.
+enum Step {
+    /// Numbers the basic block, with loads available at the beginning.
+    Enter(BasicBlock, Memory),
+    /// Leaves a basic block, its expressions are no longer available.
+    Leave(Vec<Expr>),
+}
+
+/// Numbers values by walking the dominator tree.
+struct Numbering<'a> {
+    data: &'a FunctionData,
+    cfg: &'a Cfg,
+    dom: &'a DomTree,
+    /// Available expressions, in basic blocks that dominate the current one.
+    exprs: HashMap<Expr, Value>,
+    /// Redundant instructions, and the values to replace them with.
+    leaders: HashMap<Value, Value>,
+}
+
+impl Numbering<'_> {
+    /// Numbers instructions in basic blocks dominated by the entry.
+    fn walk(&mut self, entry: BasicBlock) {
+        // explicit stack, avoids stack overflow on deep dominator trees
+        let mut stack = vec![Step::Enter(entry, Memory::new())];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Enter(bb, memory) => {
+                    let (defined, memory) = self.visit(bb, memory);
+                    stack.push(Step::Leave(defined));
+                    // loads are still available in successors with only one predecessor
+                    for &child in self.dom.children(bb).iter().rev() {
+                        let memory = if self.cfg.preds(child) == [bb] {
+                            memory.clone()
+                        } else {
+                            Memory::new()
+                        };
+                        stack.push(Step::Enter(child, memory));
+                    }
+                }
+                Step::Leave(defined) => {
+                    for expr in defined {
+                        self.exprs.remove(&expr);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Numbers instructions in the given basic block, `memory` holds loads
+    /// available at the beginning. Returns expressions defined in the basic
+    /// block, and loads available at the end.
+    fn visit(&mut self, bb: BasicBlock, mut memory: Memory) -> (Vec<Expr>, Memory) {
+        let data = self.data;
+        let node = data.layout().bbs().node(&bb).unwrap();
+        let mut defined = Vec::new();
+        for &inst in node.insts().keys() {
+            match data.dfg().value(inst).kind() {
+                ValueKind::Load(load) => {
+                    let ptr = self.num(load.src());
+                    match memory.get(&ptr) {
+                        Some(&value) => {
+                            self.leaders.insert(inst, value);
+                        }
+                        None => {
+                            memory.insert(ptr, inst);
+                        }
+                    }
+                }
+                ValueKind::Store(store) => {
+                    let ptr = self.num(store.dest());
+                    let object = self.object(store.dest());
+                    memory.retain(|&p, _| !self.may_alias(p, object));
+                    memory.insert(ptr, self.leader(store.value()));
+                }
+                ValueKind::Call(_) => memory.clear(),
+                _ => {
+                    let Some(expr) = self.expr(inst) else {
+                        continue;
+                    };
+                    match self.lookup(expr) {
+                        Some(value) => {
+                            self.leaders.insert(inst, value);
+                        }
+                        None => {
+                            self.exprs.insert(expr, inst);
+                            defined.push(expr);
+                        }
+                    }
+                }
+            }
+        }
+        (defined, memory)
+    }
+
+    /// Returns the value that the given value is replaced with.
+    fn leader(&self, value: Value) -> Value {
+        self.leaders.get(&value).copied().unwrap_or(value)
+    }
+
+    /// Returns the number of the given value.
+    fn num(&self, value: Value) -> Num {
+        match self.data.dfg().values().get(&value).map(|v| v.kind()) {
+            Some(ValueKind::Integer(i)) => Num::Int(i.value()),
+            _ => Num::Value(self.leader(value)),
+        }
+    }
+
+    /// Returns the expression computed by the given instruction,
+    /// `None` if the instruction is not pure.
+    fn expr(&self, inst: Value) -> Option<Expr> {
+        Some(match self.data.dfg().value(inst).kind() {
+            ValueKind::Binary(bin) => {
+                Expr::Binary(bin.op(), self.num(bin.lhs()), self.num(bin.rhs()))
+            }
+            ValueKind::GetPtr(gp) => Expr::GetPtr(self.num(gp.src()), self.num(gp.index())),
+            ValueKind::GetElemPtr(gep) => {
+                Expr::GetElemPtr(self.num(gep.src()), self.num(gep.index()))
+            }
+            _ => return None,
+        })
+    }
+
+    /// Returns an available value of the given expression.
+    fn lookup(&self, expr: Expr) -> Option<Value> {
+        if let Some(&value) = self.exprs.get(&expr) {
+            return Some(value);
+        }
+        // try again with operands swapped
+        match expr {
+            Expr::Binary(op, lhs, rhs) if is_commutative(op) => {
+                self.exprs.get(&Expr::Binary(op, rhs, lhs)).copied()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the object that the given pointer points into.
+    fn object(&self, mut ptr: Value) -> Object {
+        loop {
+            let Some(value) = self.data.dfg().values().get(&ptr) else {
+                return Object::Global(ptr);
+            };
+            ptr = match value.kind() {
+                ValueKind::Alloc(_) => return Object::Alloc(ptr),
+                ValueKind::GetPtr(gp) => gp.src(),
+                ValueKind::GetElemPtr(gep) => gep.src(),
+                _ => return Object::Unknown,
+            };
+        }
+    }
+
+    /// Returns `true` if the pointer of the given number may point into
+    /// the given object.
+    fn may_alias(&self, ptr: Num, object: Object) -> bool {
+        let Num::Value(ptr) = ptr else {
+            return true;
+        };
+        match (self.object(ptr), object) {
+            (Object::Alloc(a), Object::Alloc(b)) | (Object::Global(a), Object::Global(b)) => a == b,
+            // parameters can not point to allocations of the current function
+            (Object::Alloc(_), _) | (_, Object::Alloc(_)) => false,
+            _ => true,
+        }
+    }
+}
+
+/// Returns `true` if operands of the binary operation can be swapped.
+fn is_commutative(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Add
+            | BinaryOp::Mul
+            | BinaryOp::Eq
+            | BinaryOp::NotEq
+            | BinaryOp::And
+            | BinaryOp::Or
+            | BinaryOp::Xor
+    )
+}
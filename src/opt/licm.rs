@@ -0,0 +1,87 @@
+use super::cfg::{remove_unreachable_bbs, Cfg, DomTree};
+use super::loops::{find_loops, insert_preheader, Loop};
+use koopa::ir::{BasicBlock, Function, FunctionData, ValueKind};
+use koopa::opt::FunctionPass;
+use std::collections::HashSet;
+
+/// Loop-invariant code motion, hoists arithmetic and pointer calculations
+/// whose operands are defined outside the loop to the preheader.
+///
+/// Inner loops are processed first, so invariants of nested loops
+/// are hoisted out of as many loops as possible.
+pub struct Licm;
+
+impl FunctionPass for Licm {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        let entry = data.layout().entry_bb().unwrap();
+        remove_unreachable_bbs(data, &Cfg::new(data));
+        // insert preheaders one at a time, loops change after each insertion
+        loop {
+            let cfg = Cfg::new(data);
+            let loops = find_loops(&cfg, &DomTree::new(&cfg));
+            let Some(lp) = loops
+                .iter()
+                .find(|l| l.header != entry && l.preheader(&cfg).is_none())
+            else {
+                break;
+            };
+            insert_preheader(data, &cfg, lp);
+        }
+        let cfg = Cfg::new(data);
+        for lp in find_loops(&cfg, &DomTree::new(&cfg)) {
+            // the entry has no preheader
+            if lp.header != entry {
+                let preheader = lp.preheader(&cfg).unwrap();
+                hoist(data, &cfg, &lp, preheader);
+            }
+        }
+    }
+}
+
+/// Moves invariant instructions of the loop to the end of the preheader.
+fn hoist(data: &mut FunctionData, cfg: &Cfg, lp: &Loop, preheader: BasicBlock) {
+    let dfg = data.dfg();
+    let layout = data.layout();
+    let params: HashSet<_> = lp
+        .bbs
+        .iter()
+        .flat_map(|&bb| dfg.bb(bb).params().iter().copied())
+        .collect();
+    let mut invariants = Vec::new();
+    let mut hoisted = HashSet::new();
+    // definitions dominate uses, so invariant operands are found before
+    // their users in reverse post-order
+    for &bb in cfg.rpo().iter().filter(|bb| lp.bbs.contains(bb)) {
+        for &inst in layout.bbs().node(&bb).unwrap().insts().keys() {
+            let kind = dfg.value(inst).kind();
+            if !matches!(
+                kind,
+                ValueKind::Binary(_) | ValueKind::GetPtr(_) | ValueKind::GetElemPtr(_)
+            ) {
+                continue;
+            }
+            let invariant = kind.value_uses().all(|v| {
+                let in_loop = layout.parent_bb(v).is_some_and(|bb| lp.bbs.contains(&bb));
+                hoisted.contains(&v) || !(in_loop || params.contains(&v))
+            });
+            if invariant {
+                invariants.push((bb, inst));
+                hoisted.insert(inst);
+            }
+        }
+    }
+    trace!(
+        Opt,
+        "licm: hoisted {} values out of loop `{}` at depth {}",
+        invariants.len(),
+        dfg.bb(lp.header).name().as_deref().unwrap_or("<unnamed>"),
+        lp.depth
+    );
+    let term = *layout.bbs().node(&preheader).unwrap().insts().back_key().unwrap();
+    let layout = data.layout_mut();
+    for (bb, inst) in invariants {
+        layout.bb_mut(bb).insts_mut().remove(&inst);
+        let insts = layout.bb_mut(preheader).insts_mut();
+        insts.cursor_mut(term).insert_key_before(inst).unwrap();
+    }
+}
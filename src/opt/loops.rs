@@ -0,0 +1,112 @@
+use super::cfg::{Cfg, DomTree};
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BasicBlock, FunctionData, ValueKind};
+use std::collections::HashSet;
+
+/// Natural loop, loops with the same header are merged.
+pub struct Loop {
+    pub header: BasicBlock,
+    /// All basic blocks in the loop, including the header
+    /// and basic blocks of inner loops.
+    pub bbs: HashSet<BasicBlock>,
+    /// Nesting depth, 1 for outermost loops.
+    pub depth: usize,
+}
+
+impl Loop {
+    /// Returns the preheader of the loop, which is the only predecessor of
+    /// the header outside the loop, and has only the header as successor.
+    pub fn preheader(&self, cfg: &Cfg) -> Option<BasicBlock> {
+        let mut outside = cfg
+            .preds(self.header)
+            .iter()
+            .filter(|bb| !self.bbs.contains(bb));
+        match (outside.next(), outside.next()) {
+            (Some(&pred), None) if cfg.succs(pred) == [self.header] => Some(pred),
+            _ => None,
+        }
+    }
+}
+
+/// Finds all natural loops of reachable basic blocks,
+/// inner loops come before outer loops.
+pub fn find_loops(cfg: &Cfg, dom: &DomTree) -> Vec<Loop> {
+    let mut loops: Vec<Loop> = Vec::new();
+    for &bb in cfg.rpo() {
+        // predecessors dominated by the header are sources of back edges
+        let mut stack: Vec<_> = cfg
+            .preds(bb)
+            .iter()
+            .copied()
+            .filter(|&pred| dom.dominates(bb, pred))
+            .collect();
+        if stack.is_empty() {
+            continue;
+        }
+        // the loop contains basic blocks that reach back edges
+        // without passing through the header
+        let mut bbs = HashSet::from([bb]);
+        while let Some(bb) = stack.pop() {
+            if bbs.insert(bb) {
+                stack.extend(cfg.preds(bb));
+            }
+        }
+        loops.push(Loop {
+            header: bb,
+            bbs,
+            depth: 0,
+        });
+    }
+    let depths: Vec<_> = loops
+        .iter()
+        .map(|l| {
+            let outer = loops.iter().filter(|o| o.bbs.contains(&l.header));
+            outer.count()
+        })
+        .collect();
+    for (l, depth) in loops.iter_mut().zip(depths) {
+        l.depth = depth;
+    }
+    loops.sort_by_key(|l| std::cmp::Reverse(l.depth));
+    loops
+}
+
+/// Inserts a preheader before the header of the loop, and redirects
+/// all edges from outside the loop to it. Returns the preheader.
+pub fn insert_preheader(data: &mut FunctionData, cfg: &Cfg, lp: &Loop) -> BasicBlock {
+    let header = lp.header;
+    let dfg = data.dfg_mut();
+    let tys = dfg
+        .bb(header)
+        .params()
+        .iter()
+        .map(|&p| dfg.value(p).ty().clone())
+        .collect();
+    let name = dfg.bb(header).name().as_ref().map(|name| format!("{}_pre", name));
+    let preheader = dfg.new_bb().basic_block_with_params(name, tys);
+    let params = dfg.bb(preheader).params().to_vec();
+    let jump = dfg.new_value().jump_with_args(header, params);
+    let layout = data.layout_mut();
+    layout.bbs_mut().cursor_mut(header).insert_key_before(preheader).unwrap();
+    layout.bb_mut(preheader).insts_mut().push_key_back(jump).unwrap();
+    // redirect edges from outside the loop, terminators have no users
+    let redirect = |bb: &mut BasicBlock| {
+        if *bb == header {
+            *bb = preheader;
+        }
+    };
+    for &pred in cfg.preds(header).iter().filter(|bb| !lp.bbs.contains(bb)) {
+        let term = *data.layout().bbs().node(&pred).unwrap().insts().back_key().unwrap();
+        let mut value = data.dfg().value(term).clone();
+        match value.kind_mut() {
+            ValueKind::Jump(jump) => redirect(jump.target_mut()),
+            ValueKind::Branch(br) => {
+                redirect(br.true_bb_mut());
+                redirect(br.false_bb_mut());
+            }
+            _ => unreachable!(),
+        }
+        data.dfg_mut().replace_value_with(term).raw(value);
+    }
+    preheader
+}
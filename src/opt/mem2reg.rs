@@ -0,0 +1,270 @@
+use super::cfg::{remove_unreachable_bbs, Cfg, DomTree};
+use super::rewrite::{map_operands, rebuild_insts};
+use intrinsic::base_type;
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BasicBlock, Function, FunctionData, TypeKind, Value, ValueKind};
+use koopa::opt::FunctionPass;
+use std::collections::{HashMap, HashSet};
+
+/// Promotes memory to registers, replaces loads and stores of
+/// scalar allocations whose addresses never escape with SSA values.
+///
+/// Values from different control flow paths are merged by basic block
+/// parameters, inserted at the iterated dominance frontiers of stores.
+pub struct Mem2Reg;
+
+impl FunctionPass for Mem2Reg {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        // skip declarations
+        let entry = match data.layout().entry_bb() {
+            Some(entry) => entry,
+            None => return,
+        };
+        remove_unreachable_bbs(data, &Cfg::new(data));
+        let allocs = promotable_allocs(data);
+        if allocs.is_empty() {
+            return;
+        }
+        let cfg = Cfg::new(data);
+        let dom = DomTree::new(&cfg);
+        let params = insert_params(data, &cfg, &dom, &allocs);
+        // initial values of allocations are undefined
+        let undefs = allocs
+            .iter()
+            .map(|&alloc| {
+                let ty = base_type(data.dfg().value(alloc).ty());
+                (alloc, data.dfg_mut().new_value().undef(ty))
+            })
+            .collect();
+        let mut renamer = Renamer {
+            data,
+            cfg: &cfg,
+            dom: &dom,
+            allocs: allocs.iter().copied().collect(),
+            params: &params,
+            values: HashMap::new(),
+            removed: Vec::new(),
+            args: HashMap::new(),
+        };
+        renamer.rename(entry, undefs);
+        let Renamer {
+            values,
+            removed,
+            args,
+            ..
+        } = renamer;
+        // remove loads, stores and allocations from the layout
+        for &inst in removed.iter().chain(&allocs) {
+            let bb = data.layout().parent_bb(inst).unwrap();
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&inst);
+        }
+        // replace uses of loads, pass arguments to basic block parameters
+        rebuild_insts(data, |inst, value| {
+            map_operands(value.kind_mut(), |v| values.get(&v).copied().unwrap_or(v));
+            for (target, args) in args.get(&inst).into_iter().flatten() {
+                match value.kind_mut() {
+                    ValueKind::Jump(jump) => jump.args_mut().extend(args),
+                    ValueKind::Branch(br) => {
+                        if br.true_bb() == *target {
+                            br.true_args_mut().extend(args);
+                        }
+                        if br.false_bb() == *target {
+                            br.false_args_mut().extend(args);
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        });
+        // remove values, users first
+        let (stores, loads): (Vec<_>, Vec<_>) = removed
+            .into_iter()
+            .partition(|&v| matches!(data.dfg().value(v).kind(), ValueKind::Store(_)));
+        for value in stores.into_iter().chain(loads).chain(allocs) {
+            data.dfg_mut().remove_value(value);
+        }
+        let undefs: Vec<_> = data
+            .dfg()
+            .values()
+            .iter()
+            .filter(|(_, v)| matches!(v.kind(), ValueKind::Undef(_)) && v.used_by().is_empty())
+            .map(|(&v, _)| v)
+            .collect();
+        for undef in undefs {
+            data.dfg_mut().remove_value(undef);
+        }
+    }
+}
+
+/// Returns allocations that can be promoted, in layout order.
+///
+/// An allocation can be promoted if it is not an array,
+/// and it is only loaded from or stored to.
+fn promotable_allocs(data: &FunctionData) -> Vec<Value> {
+    let dfg = data.dfg();
+    data.layout()
+        .bbs()
+        .iter()
+        .flat_map(|(_, node)| node.insts().keys().copied())
+        .filter(|&inst| {
+            let value = dfg.value(inst);
+            let is_scalar = match value.ty().kind() {
+                TypeKind::Pointer(base) => !matches!(base.kind(), TypeKind::Array(..)),
+                _ => false,
+            };
+            matches!(value.kind(), ValueKind::Alloc(_))
+                && is_scalar
+                && value
+                    .used_by()
+                    .iter()
+                    .all(|&user| match dfg.value(user).kind() {
+                        ValueKind::Load(_) => true,
+                        ValueKind::Store(store) => store.dest() == inst && store.value() != inst,
+                        _ => false,
+                    })
+        })
+        .collect()
+}
+
+/// Parameters inserted to basic blocks, with their corresponding allocations.
+type Params = HashMap<BasicBlock, Vec<(Value, Value)>>;
+
+/// Inserts basic block parameters for allocations,
+/// at the iterated dominance frontiers of basic blocks that store to them.
+fn insert_params(data: &mut FunctionData, cfg: &Cfg, dom: &DomTree, allocs: &[Value]) -> Params {
+    let frontiers = dom.frontiers(cfg);
+    let mut phis: HashMap<BasicBlock, Vec<Value>> = HashMap::new();
+    for &alloc in allocs {
+        let mut defs: Vec<_> = data
+            .dfg()
+            .value(alloc)
+            .used_by()
+            .iter()
+            .filter(|&&user| matches!(data.dfg().value(user).kind(), ValueKind::Store(_)))
+            .map(|&store| data.layout().parent_bb(store).unwrap())
+            .collect();
+        let mut placed = HashSet::new();
+        let mut visited: HashSet<_> = defs.iter().copied().collect();
+        while let Some(bb) = defs.pop() {
+            for &frontier in &frontiers[&bb] {
+                if placed.insert(frontier) {
+                    phis.entry(frontier).or_default().push(alloc);
+                    if visited.insert(frontier) {
+                        defs.push(frontier);
+                    }
+                }
+            }
+        }
+    }
+    // Koopa can not create parameters for existing basic blocks,
+    // so create them in a temporary basic block, then move them
+    let mut params = HashMap::new();
+    for &bb in cfg.rpo() {
+        let allocs = match phis.remove(&bb) {
+            Some(allocs) => allocs,
+            None => continue,
+        };
+        let dfg = data.dfg_mut();
+        let old = dfg.bb(bb).params().len();
+        let tys = dfg
+            .bb(bb)
+            .params()
+            .iter()
+            .map(|&p| dfg.value(p).ty().clone())
+            .chain(allocs.iter().map(|&a| base_type(dfg.value(a).ty())))
+            .collect();
+        let temp = dfg.new_bb().basic_block_with_params(None, tys);
+        let new = dfg.bb_mut(temp).params_mut().split_off(old);
+        dfg.remove_bb(temp);
+        dfg.bb_mut(bb).params_mut().extend(&new);
+        params.insert(bb, allocs.into_iter().zip(new).collect());
+    }
+    params
+}
+
+/// Step of walking the dominator tree.
+enum Step {
+    /// Renames the basic block.
+    Enter(BasicBlock),
+    /// Leaves a basic block, restores values of allocations before it.
+    Leave(Vec<(Value, Value)>),
+}
+
+/// Renames loads and stores of allocations by walking the dominator tree.
+struct Renamer<'a> {
+    data: &'a FunctionData,
+    cfg: &'a Cfg,
+    dom: &'a DomTree,
+    allocs: HashSet<Value>,
+    params: &'a Params,
+    /// Values that loads should be replaced with.
+    values: HashMap<Value, Value>,
+    /// Loads and stores to be removed.
+    removed: Vec<Value>,
+    /// Arguments that terminators should pass to their targets.
+    args: HashMap<Value, Vec<(BasicBlock, Vec<Value>)>>,
+}
+
+impl Renamer<'_> {
+    /// Renames basic blocks dominated by the entry,
+    /// `cur` holds the initial values of all allocations.
+    fn rename(&mut self, entry: BasicBlock, mut cur: HashMap<Value, Value>) {
+        // explicit stack, avoids stack overflow on deep dominator trees,
+        // values defined in a basic block are undone when leaving it
+        let mut stack = vec![Step::Enter(entry)];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Enter(bb) => {
+                    let undo = self.rename_bb(bb, &mut cur);
+                    stack.push(Step::Leave(undo));
+                    let children = self.dom.children(bb).iter().rev();
+                    stack.extend(children.map(|&child| Step::Enter(child)));
+                }
+                Step::Leave(undo) => {
+                    for (alloc, value) in undo.into_iter().rev() {
+                        cur.insert(alloc, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Renames the given basic block, updates current values of allocations.
+    /// Returns the replaced values, in the order they were replaced.
+    fn rename_bb(
+        &mut self,
+        bb: BasicBlock,
+        cur: &mut HashMap<Value, Value>,
+    ) -> Vec<(Value, Value)> {
+        let mut undo = Vec::new();
+        for &(alloc, param) in self.params.get(&bb).into_iter().flatten() {
+            undo.push((alloc, cur.insert(alloc, param).unwrap()));
+        }
+        let data = self.data;
+        let node = data.layout().bbs().node(&bb).unwrap();
+        for &inst in node.insts().keys() {
+            match data.dfg().value(inst).kind() {
+                ValueKind::Load(load) if self.allocs.contains(&load.src()) => {
+                    self.values.insert(inst, cur[&load.src()]);
+                    self.removed.push(inst);
+                }
+                ValueKind::Store(store) if self.allocs.contains(&store.dest()) => {
+                    let value = store.value();
+                    let value = self.values.get(&value).copied().unwrap_or(value);
+                    undo.push((store.dest(), cur.insert(store.dest(), value).unwrap()));
+                    self.removed.push(inst);
+                }
+                _ => {}
+            }
+        }
+        // pass current values to parameters of successors
+        let term = *node.insts().back_key().unwrap();
+        for &succ in self.cfg.succs(bb) {
+            if let Some(params) = self.params.get(&succ) {
+                let args = params.iter().map(|(alloc, _)| cur[alloc]).collect();
+                self.args.entry(term).or_default().push((succ, args));
+            }
+        }
+        undo
+    }
+}
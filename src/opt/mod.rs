@@ -0,0 +1,170 @@
+mod cfg;
+mod dce;
+pub(crate) mod fold;
+mod gvn;
+mod licm;
+mod loops;
+mod mem2reg;
+mod rewrite;
+mod sccp;
+mod simplify;
+
+use koopa::back::KoopaGenerator;
+use koopa::ir::Program;
+use koopa::opt::Pass;
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Optimization level, which selects the passes to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimization.
+    O0,
+    /// Cheap optimizations.
+    O1,
+    /// All optimizations.
+    O2,
+}
+
+impl OptLevel {
+    /// All optimization levels.
+    pub const ALL: &'static [OptLevel] = &[Self::O0, Self::O1, Self::O2];
+
+    /// Returns the optimization level of the given name, e.g. `1` for `-O1`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|l| l.name() == name)
+    }
+
+    /// Returns the name of the optimization level.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::O0 => "0",
+            Self::O1 => "1",
+            Self::O2 => "2",
+        }
+    }
+
+    /// Returns the passes run at this level, in order.
+    pub fn passes(self) -> &'static [&'static str] {
+        match self {
+            Self::O0 => &[],
+            Self::O1 => &["mem2reg", "fold", "dce", "simplify-cfg", "dce"],
+            Self::O2 => &[
+                "mem2reg",
+                "gvn",
+                "sccp",
+                "dce",
+                "simplify-cfg",
+                "licm",
+                "gvn",
+                "dce",
+                "simplify-cfg",
+            ],
+        }
+    }
+}
+
+/// Pipeline of optimization passes, which are run in order.
+///
+/// Function passes are run on all function definitions before the next
+/// pass starts, and module passes are run on the whole program.
+pub struct Pipeline {
+    passes: Vec<(String, Pass)>,
+    /// Sink of Koopa IR dumped after each pass.
+    dump: Option<Box<dyn Write>>,
+}
+
+impl Pipeline {
+    /// Creates a new pipeline of the given passes.
+    /// Returns the name of the first unknown pass if there is one.
+    pub fn new<S: AsRef<str>>(passes: &[S]) -> Result<Self, String> {
+        let passes = passes
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+                new_pass(name).map(|p| (name.into(), p)).ok_or_else(|| name.to_string())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { passes, dump: None })
+    }
+
+    /// Dumps the Koopa IR to `out` after each pass.
+    pub fn dump_to(mut self, out: Box<dyn Write>) -> Self {
+        self.dump = Some(out);
+        self
+    }
+
+    /// Runs all passes on the program, returns the time spent in each pass.
+    /// Errors are returned only if the Koopa IR can not be dumped.
+    pub fn run(&mut self, program: &mut Program) -> io::Result<Timings> {
+        let names: Vec<_> = self.passes.iter().map(|(name, _)| name.as_str()).collect();
+        trace!(Opt, "running passes: {}", names.join(", "));
+        let mut timings = Vec::new();
+        for (name, pass) in &mut self.passes {
+            let start = Instant::now();
+            match pass {
+                Pass::Module(p) => p.run_on(program),
+                Pass::Function(p) => {
+                    for (&func, data) in program.funcs_mut() {
+                        // skip declarations
+                        if data.layout().entry_bb().is_some() {
+                            p.run_on(func, data);
+                        }
+                    }
+                }
+            }
+            let elapsed = start.elapsed();
+            trace!(Opt, "pass `{}` finished in {:?}", name, elapsed);
+            timings.push((name.clone(), elapsed));
+            if let Some(out) = &mut self.dump {
+                writeln!(out, "// Koopa IR after pass `{}`", name)?;
+                KoopaGenerator::new(&mut *out).generate_on(program)?;
+                writeln!(out)?;
+                out.flush()?;
+            }
+        }
+        Ok(Timings(timings))
+    }
+}
+
+/// Time spent in each pass of a pipeline, in the order they ran.
+#[derive(Debug, Clone, Default)]
+pub struct Timings(pub Vec<(String, Duration)>);
+
+impl Timings {
+    /// Returns the total time spent in all passes.
+    pub fn total(&self) -> Duration {
+        self.0.iter().map(|(_, time)| *time).sum()
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.0.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        let width = width.max("total".len());
+        for (name, time) in &self.0 {
+            writeln!(f, "{:width$}  {:>10.3}ms", name, ms(*time), width = width)?;
+        }
+        write!(f, "{:width$}  {:>10.3}ms", "total", ms(self.total()), width = width)
+    }
+}
+
+/// Converts the duration into milliseconds.
+fn ms(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+/// Creates a new pass by the given name.
+fn new_pass(name: &str) -> Option<Pass> {
+    match name {
+        "mem2reg" => Some(Pass::Function(Box::new(mem2reg::Mem2Reg))),
+        "fold" => Some(Pass::Function(Box::new(fold::ConstFold))),
+        "simplify-cfg" => Some(Pass::Function(Box::new(simplify::SimplifyCfg))),
+        "dce" => Some(Pass::Function(Box::new(dce::Dce))),
+        "sccp" => Some(Pass::Function(Box::new(sccp::Sccp))),
+        "gvn" => Some(Pass::Function(Box::new(gvn::Gvn))),
+        "licm" => Some(Pass::Function(Box::new(licm::Licm))),
+        _ => None,
+    }
+}
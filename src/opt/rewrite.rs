@@ -0,0 +1,121 @@
+use koopa::ir::builder_traits::*;
+use koopa::ir::entities::ValueData;
+use koopa::ir::{FunctionData, Value, ValueKind};
+use std::collections::HashSet;
+
+/// Rebuilds all instructions in the layout of the function,
+/// calls `f` to modify the data of each instruction before rebuilding.
+///
+/// Replacing a value in Koopa clears the set of its users, so instructions
+/// are rebuilt in the order that definitions come before uses,
+/// users will be logged again when they are rebuilt.
+pub fn rebuild_insts<F>(data: &mut FunctionData, mut f: F)
+where
+    F: FnMut(Value, &mut ValueData),
+{
+    let insts: Vec<_> = data
+        .layout()
+        .bbs()
+        .iter()
+        .flat_map(|(_, node)| node.insts().keys().copied())
+        .collect();
+    let in_layout: HashSet<_> = insts.iter().copied().collect();
+    // sort instructions topologically, by iterative DFS on operands
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    for inst in insts {
+        if !visited.insert(inst) {
+            continue;
+        }
+        let mut stack = vec![(inst, false)];
+        while let Some((value, expanded)) = stack.pop() {
+            if expanded {
+                order.push(value);
+                continue;
+            }
+            stack.push((value, true));
+            for op in data.dfg().value(value).kind().value_uses() {
+                if in_layout.contains(&op) && visited.insert(op) {
+                    stack.push((op, false));
+                }
+            }
+        }
+    }
+    for inst in order {
+        let mut value = data.dfg().value(inst).clone();
+        f(inst, &mut value);
+        data.dfg_mut().replace_value_with(inst).raw(value);
+    }
+}
+
+/// Removes the given values from the data flow graph, which have been
+/// removed from the layout and are only used by each other.
+///
+/// Values are removed in one pass, users first: a value is removed once
+/// its last user has been removed.
+pub fn remove_values(data: &mut FunctionData, values: Vec<Value>) {
+    let pending: HashSet<_> = values.iter().copied().collect();
+    let mut worklist: Vec<_> = values
+        .into_iter()
+        .filter(|&v| data.dfg().value(v).used_by().is_empty())
+        .collect();
+    let mut removed: HashSet<_> = worklist.iter().copied().collect();
+    while let Some(value) = worklist.pop() {
+        let ops: Vec<_> = data.dfg().value(value).kind().value_uses().collect();
+        data.dfg_mut().remove_value(value);
+        for op in ops {
+            if pending.contains(&op)
+                && data.dfg().value(op).used_by().is_empty()
+                && removed.insert(op)
+            {
+                worklist.push(op);
+            }
+        }
+    }
+    assert!(
+        removed.len() == pending.len(),
+        "removed value is used by other values"
+    );
+}
+
+/// Maps all operands of the given value kind by applying `f`.
+pub fn map_operands<F>(kind: &mut ValueKind, mut f: F)
+where
+    F: FnMut(Value) -> Value,
+{
+    let mut map = |v: &mut Value| *v = f(*v);
+    match kind {
+        ValueKind::Aggregate(v) => v.elems_mut().iter_mut().for_each(map),
+        ValueKind::GlobalAlloc(v) => map(v.init_mut()),
+        ValueKind::Load(v) => map(v.src_mut()),
+        ValueKind::Store(v) => {
+            map(v.value_mut());
+            map(v.dest_mut());
+        }
+        ValueKind::GetPtr(v) => {
+            map(v.src_mut());
+            map(v.index_mut());
+        }
+        ValueKind::GetElemPtr(v) => {
+            map(v.src_mut());
+            map(v.index_mut());
+        }
+        ValueKind::Binary(v) => {
+            map(v.lhs_mut());
+            map(v.rhs_mut());
+        }
+        ValueKind::Branch(v) => {
+            map(v.cond_mut());
+            v.true_args_mut().iter_mut().for_each(&mut map);
+            v.false_args_mut().iter_mut().for_each(&mut map);
+        }
+        ValueKind::Jump(v) => v.args_mut().iter_mut().for_each(map),
+        ValueKind::Call(v) => v.args_mut().iter_mut().for_each(map),
+        ValueKind::Return(v) => {
+            if let Some(v) = v.value_mut() {
+                map(v);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,245 @@
+use super::cfg::{incoming_args, remove_unreachable_bbs, Cfg};
+use super::fold::eval_binary;
+use super::rewrite::{map_operands, rebuild_insts, remove_values};
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BasicBlock, BinaryOp, Function, FunctionData, Value, ValueKind};
+use koopa::opt::FunctionPass;
+use std::collections::{HashMap, HashSet};
+
+/// Sparse conditional constant propagation, by Wegman and Zadeck.
+///
+/// Values are assumed to be undetermined until proven otherwise, and only
+/// basic blocks reachable through executable edges are evaluated, so
+/// constants are propagated through basic block parameters and branches
+/// whose conditions are constants. Constant values are replaced with
+/// integers, and constant branches are replaced with jumps.
+pub struct Sccp;
+
+impl FunctionPass for Sccp {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        let entry = data.layout().entry_bb().unwrap();
+        let mut solver = Solver {
+            data,
+            values: HashMap::new(),
+            edges: HashSet::new(),
+            executable: HashSet::new(),
+            flow: Vec::new(),
+            ssa: Vec::new(),
+        };
+        solver.solve(entry);
+        let Solver { values, edges, .. } = solver;
+        // branches with only one executable edge
+        let mut folds = Vec::new();
+        for (&bb, node) in data.layout().bbs() {
+            let term = *node.insts().back_key().unwrap();
+            if let ValueKind::Branch(br) = data.dfg().value(term).kind() {
+                match (edges.contains(&(bb, br.true_bb())), edges.contains(&(bb, br.false_bb()))) {
+                    (true, false) => folds.push((term, true)),
+                    (false, true) => folds.push((term, false)),
+                    _ => {}
+                }
+            }
+        }
+        let consts: HashMap<_, _> = values
+            .into_iter()
+            .filter_map(|(v, l)| match l {
+                Lattice::Const(num) => Some((v, num)),
+                _ => None,
+            })
+            .collect();
+        if consts.is_empty() && folds.is_empty() {
+            return;
+        }
+        trace!(Opt, "sccp: {} constants, {} branches", consts.len(), folds.len());
+        // replace constant values with integers, constant parameters are
+        // left for dead code elimination
+        let mut insts = Vec::new();
+        let mut ints = HashMap::new();
+        for (&value, &num) in &consts {
+            ints.insert(value, data.dfg_mut().new_value().integer(num));
+            if let Some(bb) = data.layout().parent_bb(value) {
+                data.layout_mut().bb_mut(bb).insts_mut().remove(&value);
+                insts.push(value);
+            }
+        }
+        rebuild_insts(data, |_, value| {
+            map_operands(value.kind_mut(), |v| ints.get(&v).copied().unwrap_or(v))
+        });
+        for (term, taken) in folds {
+            let ValueKind::Branch(br) = data.dfg().value(term).kind() else {
+                unreachable!()
+            };
+            let (target, args) = if taken {
+                (br.true_bb(), br.true_args().to_vec())
+            } else {
+                (br.false_bb(), br.false_args().to_vec())
+            };
+            data.dfg_mut()
+                .replace_value_with(term)
+                .jump_with_args(target, args);
+        }
+        remove_unreachable_bbs(data, &Cfg::new(data));
+        // constant instructions are only used by each other
+        remove_values(data, insts);
+    }
+}
+
+/// Lattice of values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lattice {
+    /// Not determined yet.
+    Top,
+    Const(i32),
+    /// Not a constant.
+    Bottom,
+}
+
+impl Lattice {
+    /// Returns the greatest lower bound of two lattices.
+    fn meet(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Top, l) | (l, Self::Top) => l,
+            (Self::Const(a), Self::Const(b)) if a == b => self,
+            _ => Self::Bottom,
+        }
+    }
+
+    /// Evaluates the binary operation on the lattices.
+    fn binary(op: BinaryOp, lhs: Self, rhs: Self) -> Self {
+        match (lhs, rhs) {
+            (Self::Const(l), Self::Const(r)) => Self::Const(eval_binary(op, l, r)),
+            (Self::Bottom, _) | (_, Self::Bottom) => Self::Bottom,
+            _ => Self::Top,
+        }
+    }
+}
+
+/// Solver of lattices of values and executable edges.
+struct Solver<'a> {
+    data: &'a FunctionData,
+    /// Lattices of instructions and parameters, `Top` if absent.
+    values: HashMap<Value, Lattice>,
+    edges: HashSet<(BasicBlock, BasicBlock)>,
+    executable: HashSet<BasicBlock>,
+    /// Edges that just became executable.
+    flow: Vec<(BasicBlock, BasicBlock)>,
+    /// Values whose lattices just changed.
+    ssa: Vec<Value>,
+}
+
+impl Solver<'_> {
+    /// Evaluates the function from the entry until nothing changes.
+    fn solve(&mut self, entry: BasicBlock) {
+        self.visit_bb(entry);
+        loop {
+            if let Some((_, bb)) = self.flow.pop() {
+                self.update_params(bb);
+                self.visit_bb(bb);
+            } else if let Some(value) = self.ssa.pop() {
+                let layout = self.data.layout();
+                for &user in self.data.dfg().value(value).used_by() {
+                    let bb = layout.parent_bb(user);
+                    if bb.is_some_and(|bb| self.executable.contains(&bb)) {
+                        self.visit_inst(user);
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Returns the lattice of the given value.
+    fn lattice(&self, value: Value) -> Lattice {
+        // global values are pointers, which are not constants
+        let Some(data) = self.data.dfg().values().get(&value) else {
+            return Lattice::Bottom;
+        };
+        match data.kind() {
+            ValueKind::Integer(i) => Lattice::Const(i.value()),
+            // undefined values may be anything, which are assumed to be
+            // not constants, so that branches on them have targets
+            ValueKind::Undef(_) | ValueKind::FuncArgRef(_) => Lattice::Bottom,
+            _ => self.values.get(&value).copied().unwrap_or(Lattice::Top),
+        }
+    }
+
+    /// Updates the lattice of the given value.
+    fn set(&mut self, value: Value, lattice: Lattice) {
+        if self.lattice(value) != lattice {
+            self.values.insert(value, lattice);
+            self.ssa.push(value);
+        }
+    }
+
+    /// Marks the edge as executable.
+    fn add_edge(&mut self, from: BasicBlock, to: BasicBlock) {
+        if self.edges.insert((from, to)) {
+            self.flow.push((from, to));
+        }
+    }
+
+    /// Evaluates all instructions in the basic block if it has not been
+    /// evaluated.
+    fn visit_bb(&mut self, bb: BasicBlock) {
+        if self.executable.insert(bb) {
+            let node = self.data.layout().bbs().node(&bb).unwrap();
+            for &inst in node.insts().keys() {
+                self.visit_inst(inst);
+            }
+        }
+    }
+
+    /// Evaluates the instruction.
+    fn visit_inst(&mut self, inst: Value) {
+        let bb = self.data.layout().parent_bb(inst).unwrap();
+        match self.data.dfg().value(inst).kind() {
+            ValueKind::Binary(bin) => {
+                let (lhs, rhs) = (self.lattice(bin.lhs()), self.lattice(bin.rhs()));
+                self.set(inst, Lattice::binary(bin.op(), lhs, rhs));
+            }
+            ValueKind::Jump(jump) => {
+                self.add_edge(bb, jump.target());
+                self.update_params(jump.target());
+            }
+            ValueKind::Branch(br) => {
+                let targets = match self.lattice(br.cond()) {
+                    Lattice::Top => vec![],
+                    Lattice::Const(0) => vec![br.false_bb()],
+                    Lattice::Const(_) => vec![br.true_bb()],
+                    Lattice::Bottom => vec![br.true_bb(), br.false_bb()],
+                };
+                for target in targets {
+                    self.add_edge(bb, target);
+                    self.update_params(target);
+                }
+            }
+            _ => self.set(inst, Lattice::Bottom),
+        }
+    }
+
+    /// Updates parameters of the basic block by arguments passed from
+    /// all executable edges.
+    fn update_params(&mut self, bb: BasicBlock) {
+        let dfg = self.data.dfg();
+        let params = dfg.bb(bb).params();
+        if params.is_empty() {
+            return;
+        }
+        let mut lattices = vec![Lattice::Top; params.len()];
+        for &user in dfg.bb(bb).used_by() {
+            let from = self.data.layout().parent_bb(user);
+            if !from.is_some_and(|from| self.edges.contains(&(from, bb))) {
+                continue;
+            }
+            for args in incoming_args(self.data, user, bb) {
+                for (lattice, &arg) in lattices.iter_mut().zip(args) {
+                    *lattice = lattice.meet(self.lattice(arg));
+                }
+            }
+        }
+        for (&param, lattice) in params.iter().zip(lattices) {
+            self.set(param, lattice);
+        }
+    }
+}
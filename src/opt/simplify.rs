@@ -0,0 +1,198 @@
+use super::cfg::{remove_unreachable_bbs, Cfg};
+use super::rewrite::{map_operands, rebuild_insts};
+use koopa::ir::builder_traits::*;
+use koopa::ir::{BasicBlock, Function, FunctionData, Value, ValueKind};
+use koopa::opt::FunctionPass;
+use std::collections::{HashMap, HashSet};
+
+/// Control flow graph simplification.
+///
+/// Replaces branches on constants with jumps, threads jumps through
+/// basic blocks that only contain a jump, removes unreachable basic blocks,
+/// and merges basic blocks into their only predecessors.
+pub struct SimplifyCfg;
+
+impl FunctionPass for SimplifyCfg {
+    fn run_on(&mut self, _: Function, data: &mut FunctionData) {
+        // each step may enable the others, repeat until nothing changes
+        loop {
+            let mut changed = fold_branches(data);
+            changed |= thread_jumps(data);
+            let cfg = Cfg::new(data);
+            changed |= cfg.rpo().len() < data.layout().bbs().len();
+            remove_unreachable_bbs(data, &cfg);
+            changed |= merge_bbs(data);
+            if !changed {
+                break;
+            }
+        }
+    }
+}
+
+/// Returns terminators of all basic blocks in the layout.
+fn terminators(data: &FunctionData) -> Vec<Value> {
+    data.layout()
+        .bbs()
+        .nodes()
+        .filter_map(|node| node.insts().back_key().copied())
+        .collect()
+}
+
+/// Replaces branches whose condition is a constant, or whose targets are
+/// the same with the same arguments, with jumps.
+fn fold_branches(data: &mut FunctionData) -> bool {
+    let mut changed = false;
+    for term in terminators(data) {
+        let ValueKind::Branch(br) = data.dfg().value(term).kind() else {
+            continue;
+        };
+        let (target, args) = match data.dfg().value(br.cond()).kind() {
+            ValueKind::Integer(i) if i.value() != 0 => (br.true_bb(), br.true_args()),
+            ValueKind::Integer(_) => (br.false_bb(), br.false_args()),
+            _ if br.true_bb() == br.false_bb() && br.true_args() == br.false_args() => {
+                (br.true_bb(), br.true_args())
+            }
+            _ => continue,
+        };
+        let args = args.to_vec();
+        data.dfg_mut()
+            .replace_value_with(term)
+            .jump_with_args(target, args);
+        changed = true;
+    }
+    changed
+}
+
+/// Redirects jumps and branches to basic blocks that only contain a jump
+/// to the final targets of the jumps.
+fn thread_jumps(data: &mut FunctionData) -> bool {
+    let entry = data.layout().entry_bb().unwrap();
+    let dfg = data.dfg();
+    // basic blocks that only jump to another one, and whose parameters
+    // are only passed to the target
+    let forwards: HashMap<_, _> = data
+        .layout()
+        .bbs()
+        .iter()
+        .filter(|&(&bb, node)| bb != entry && node.insts().len() == 1)
+        .filter_map(|(&bb, node)| {
+            let term = *node.insts().back_key().unwrap();
+            let ValueKind::Jump(jump) = dfg.value(term).kind() else {
+                return None;
+            };
+            let params = dfg.bb(bb).params();
+            let local = params.iter().all(|&p| {
+                let users = dfg.value(p).used_by();
+                users.iter().all(|&user| user == term)
+            });
+            local.then(|| (bb, (jump.target(), jump.args().to_vec())))
+        })
+        .collect();
+    if forwards.is_empty() {
+        return false;
+    }
+    // follows the jumps from the given edge, returns the final edge
+    let resolve = |mut target: BasicBlock, mut args: Vec<Value>| {
+        let mut visited = HashSet::new();
+        while visited.insert(target) {
+            let Some((next, next_args)) = forwards.get(&target) else {
+                break;
+            };
+            let params: HashMap<_, _> = dfg.bb(target).params().iter().zip(args).collect();
+            args = next_args
+                .iter()
+                .map(|a| params.get(a).copied().unwrap_or(*a))
+                .collect();
+            target = *next;
+        }
+        (target, args)
+    };
+    let mut threaded = Vec::new();
+    for term in terminators(data) {
+        match dfg.value(term).kind() {
+            ValueKind::Jump(jump) if forwards.contains_key(&jump.target()) => {
+                let (target, args) = resolve(jump.target(), jump.args().to_vec());
+                if target != jump.target() {
+                    threaded.push((term, None, (target, args), None));
+                }
+            }
+            ValueKind::Branch(br)
+                if forwards.contains_key(&br.true_bb()) || forwards.contains_key(&br.false_bb()) =>
+            {
+                let t = resolve(br.true_bb(), br.true_args().to_vec());
+                let f = resolve(br.false_bb(), br.false_args().to_vec());
+                if t.0 != br.true_bb() || f.0 != br.false_bb() {
+                    threaded.push((term, Some(br.cond()), t, Some(f)));
+                }
+            }
+            _ => {}
+        }
+    }
+    let changed = !threaded.is_empty();
+    for (term, cond, (target, args), other) in threaded {
+        let builder = data.dfg_mut().replace_value_with(term);
+        match (cond, other) {
+            (Some(cond), Some((fbb, fargs))) => {
+                builder.branch_with_args(cond, target, fbb, args, fargs);
+            }
+            _ => {
+                builder.jump_with_args(target, args);
+            }
+        }
+    }
+    changed
+}
+
+/// Merges basic blocks into their predecessors, if the predecessor jumps
+/// to the basic block and the jump is its only use.
+fn merge_bbs(data: &mut FunctionData) -> bool {
+    let entry = data.layout().entry_bb().unwrap();
+    // parameters of merged basic blocks, and their arguments
+    let mut args = HashMap::new();
+    let mut merged = Vec::new();
+    let bbs: Vec<_> = data.layout().bbs().keys().copied().collect();
+    for bb in bbs {
+        if data.layout().bbs().node(&bb).is_none() {
+            continue;
+        }
+        loop {
+            let term = *data.layout().bbs().node(&bb).unwrap().insts().back_key().unwrap();
+            let ValueKind::Jump(jump) = data.dfg().value(term).kind() else {
+                break;
+            };
+            let succ = jump.target();
+            let users = data.dfg().bb(succ).used_by();
+            if succ == bb || succ == entry || users.len() != 1 {
+                break;
+            }
+            let params = data.dfg().bb(succ).params().iter().copied();
+            args.extend(params.zip(jump.args().iter().copied()));
+            // move instructions of the successor to the end
+            data.layout_mut().bb_mut(bb).insts_mut().remove(&term);
+            data.dfg_mut().remove_value(term);
+            let layout = data.layout_mut();
+            while let Some((inst, _)) = layout.bb_mut(succ).insts_mut().pop_front() {
+                layout.bb_mut(bb).insts_mut().push_key_back(inst).unwrap();
+            }
+            layout.bbs_mut().remove(&succ);
+            merged.push(succ);
+        }
+    }
+    if merged.is_empty() {
+        return false;
+    }
+    // replace uses of parameters with arguments, which may also be
+    // parameters of other merged basic blocks
+    rebuild_insts(data, |_, value| {
+        map_operands(value.kind_mut(), |mut v| {
+            while let Some(&arg) = args.get(&v) {
+                v = arg;
+            }
+            v
+        })
+    });
+    for bb in merged {
+        data.dfg_mut().remove_bb(bb);
+    }
+    true
+}
@@ -0,0 +1,303 @@
+use crate::ast::{CompUnit, Span};
+use lalrpop_util::lexer::{MatcherBuilder, Token};
+use lalrpop_util::{lalrpop_mod, ParseError};
+use std::fmt;
+
+// 引用 lalrpop 生成的解析器
+// 因为我们刚刚创建了 sysy.lalrpop, 所以模块名是 sysy
+lalrpop_mod!(#[allow(clippy::all)] sysy);
+
+/// Parses the given SysY source into a compile unit (ASTs).
+///
+/// The parser recovers from syntax errors at statement level,
+/// so all syntax errors found in the source are returned.
+pub fn parse(text: &str) -> Result<CompUnit> {
+    let mut recovered = Vec::new();
+    let result = sysy::CompUnitParser::new().parse(&mut recovered, text);
+    let mut errors: Vec<_> = recovered
+        .into_iter()
+        .map(|r| Error::new(text, r.error))
+        .collect();
+    match result {
+        Ok(comp_unit) if errors.is_empty() => Ok(comp_unit),
+        Ok(_) => Err(errors),
+        Err(err) => {
+            errors.push(Error::new(text, err));
+            Err(errors)
+        }
+    }
+}
+
+/// Splits the given SysY source into tokens, skipping whitespaces and comments.
+/// Returns the first lexical error if there is one.
+pub fn tokenize(text: &str) -> std::result::Result<Vec<Lexeme<'_>>, Error> {
+    let rules = LEXER_RULES.iter().map(|(re, kind)| (*re, kind.is_none()));
+    let builder = MatcherBuilder::new(rules).unwrap();
+    builder
+        .matcher::<Error>(text)
+        .map(|token| {
+            let (l, Token(index, tok), r) = token.map_err(|err| Error::new(text, err))?;
+            Ok(Lexeme {
+                kind: LEXER_RULES[index].1.unwrap(),
+                text: tok,
+                span: Span::new(l, r),
+            })
+        })
+        .collect()
+}
+
+/// Rules of the lexer, in the same form as the lexer generated from the
+/// `match` block and terminals of `sysy.lalrpop`, which is not public.
+/// Tokens of kind `None` are skipped.
+///
+/// The longest match wins, and the later rule wins if there is a tie,
+/// so keywords and punctuations come after regular expressions.
+const LEXER_RULES: &[(&str, Option<TokenKind>)] = &[
+    (r"\s*", None),
+    (r"//[^\n\r]*[\n\r]*", None),
+    (r"/\*[^*]*\*+(?:[^/*][^*]*\*+)*/", None),
+    (r"[_a-zA-Z][_a-zA-Z0-9]*", Some(TokenKind::Ident)),
+    (r"[1-9][0-9]*", Some(TokenKind::Int)),
+    (r"0[0-7]*", Some(TokenKind::Int)),
+    (r"0[xX][0-9a-fA-F]+", Some(TokenKind::Int)),
+    (
+        r"([0-9]*\.[0-9]+|[0-9]+\.)([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+",
+        Some(TokenKind::Float),
+    ),
+    (
+        r"0[xX]([0-9a-fA-F]*\.[0-9a-fA-F]+|[0-9a-fA-F]+\.?)[pP][+-]?[0-9]+",
+        Some(TokenKind::Float),
+    ),
+    ("break", Some(TokenKind::Keyword)),
+    ("const", Some(TokenKind::Keyword)),
+    ("continue", Some(TokenKind::Keyword)),
+    ("else", Some(TokenKind::Keyword)),
+    ("float", Some(TokenKind::Keyword)),
+    ("if", Some(TokenKind::Keyword)),
+    ("int", Some(TokenKind::Keyword)),
+    ("return", Some(TokenKind::Keyword)),
+    ("void", Some(TokenKind::Keyword)),
+    ("while", Some(TokenKind::Keyword)),
+    (r"!", Some(TokenKind::Punct)),
+    (r"!=", Some(TokenKind::Punct)),
+    (r"%", Some(TokenKind::Punct)),
+    (r"&&", Some(TokenKind::Punct)),
+    (r"\(", Some(TokenKind::Punct)),
+    (r"\)", Some(TokenKind::Punct)),
+    (r"\*", Some(TokenKind::Punct)),
+    (r"\+", Some(TokenKind::Punct)),
+    (r",", Some(TokenKind::Punct)),
+    (r"-", Some(TokenKind::Punct)),
+    (r"/", Some(TokenKind::Punct)),
+    (r";", Some(TokenKind::Punct)),
+    (r"<", Some(TokenKind::Punct)),
+    (r"<=", Some(TokenKind::Punct)),
+    (r"=", Some(TokenKind::Punct)),
+    (r"==", Some(TokenKind::Punct)),
+    (r">", Some(TokenKind::Punct)),
+    (r">=", Some(TokenKind::Punct)),
+    (r"\[", Some(TokenKind::Punct)),
+    (r"\]", Some(TokenKind::Punct)),
+    (r"\{", Some(TokenKind::Punct)),
+    (r"\|\|", Some(TokenKind::Punct)),
+    (r"\}", Some(TokenKind::Punct)),
+];
+
+/// A token in the source.
+#[derive(Debug, Clone, Copy)]
+pub struct Lexeme<'s> {
+    pub kind: TokenKind,
+    pub text: &'s str,
+    pub span: Span,
+}
+
+/// Kind of a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Ident,
+    Int,
+    Float,
+    Punct,
+}
+
+impl TokenKind {
+    /// Returns the name of the token kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::Ident => "ident",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Punct => "punct",
+        }
+    }
+}
+
+/// Result type of parser.
+pub type Result<T> = std::result::Result<T, Vec<Error>>;
+
+/// Syntax error returned by parser.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+/// Kind of the syntax error.
+#[derive(Debug)]
+pub enum ErrorKind {
+    InvalidToken(String),
+    UnterminatedComment,
+    UnrecognizedToken { found: String, expected: Vec<String> },
+    UnexpectedEof { expected: Vec<String> },
+    ExtraToken(String),
+    IntTooLarge(String),
+}
+
+impl Error {
+    /// Converts the LALRPOP parse error into a syntax error.
+    fn new(text: &str, err: ParseError<usize, Token, Error>) -> Self {
+        let (kind, span) = match err {
+            ParseError::InvalidToken { location } => {
+                let found = text[location..].chars().next().map_or(0, char::len_utf8);
+                let kind = match comment_start(text, location) {
+                    Some(_) => ErrorKind::UnterminatedComment,
+                    None => ErrorKind::InvalidToken(text[location..location + found].into()),
+                };
+                (kind, Span::new(location, location + found))
+            }
+            ParseError::UnrecognizedEof { location, expected } => {
+                let expected = describe_expected(&expected);
+                (ErrorKind::UnexpectedEof { expected }, Span::new(location, location))
+            }
+            ParseError::UnrecognizedToken {
+                token: (l, Token(_, found), r),
+                expected,
+            } => match comment_start(text, l) {
+                Some(start) => (ErrorKind::UnterminatedComment, Span::new(start, start + 2)),
+                None => {
+                    let found = found.into();
+                    let expected = describe_expected(&expected);
+                    (ErrorKind::UnrecognizedToken { found, expected }, Span::new(l, r))
+                }
+            },
+            ParseError::ExtraToken {
+                token: (l, Token(_, found), r),
+            } => (ErrorKind::ExtraToken(found.into()), Span::new(l, r)),
+            ParseError::User { error } => return error,
+        };
+        Self { kind, span }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::InvalidToken(found) => write!(f, "invalid token `{}`", found),
+            ErrorKind::UnterminatedComment => write!(f, "unterminated block comment"),
+            ErrorKind::UnrecognizedToken { found, expected } => {
+                write!(f, "unexpected `{}`", found)?;
+                fmt_expected(f, expected)
+            }
+            ErrorKind::UnexpectedEof { expected } => {
+                write!(f, "unexpected end of file")?;
+                fmt_expected(f, expected)
+            }
+            ErrorKind::ExtraToken(found) => write!(f, "extra token `{}`", found),
+            ErrorKind::IntTooLarge(lit) => write!(f, "integer literal `{}` is too large", lit),
+        }
+    }
+}
+
+/// Returns the start of an unterminated block comment
+/// if the given position is at `/*` or right after its `/`.
+///
+/// The lexer only skips terminated comments, an unterminated one
+/// is lexed as a `/` token followed by a `*` token.
+fn comment_start(text: &str, pos: usize) -> Option<usize> {
+    if text[pos..].starts_with("/*") {
+        Some(pos)
+    } else if text[pos..].starts_with('*') && text[..pos].ends_with('/') {
+        Some(pos - 1)
+    } else {
+        None
+    }
+}
+
+/// Converts the expected terminal names in grammar into
+/// human-readable descriptions.
+fn describe_expected(expected: &[String]) -> Vec<String> {
+    let mut descs: Vec<String> = Vec::new();
+    for e in expected {
+        let desc = if e.starts_with("r#") {
+            // regular expression terminals
+            if e.contains("_a-zA-Z") {
+                "identifier".into()
+            } else if e.contains("eE") || e.contains("pP") {
+                "float literal".into()
+            } else {
+                "integer literal".into()
+            }
+        } else {
+            format!("`{}`", e.trim_matches('"'))
+        };
+        if !descs.contains(&desc) {
+            descs.push(desc);
+        }
+    }
+    descs
+}
+
+/// Converts an integer literal into `u32`.
+///
+/// Decimal literals may be up to 2^31 so that `-2147483648` can be written,
+/// octal and hexadecimal ones may be up to 2^32 - 1. Values greater than
+/// `i32::MAX` are left to semantic analysis, which wraps them around to
+/// negative numbers and warns about the overflow.
+pub fn int_literal(text: &str, span: Span) -> std::result::Result<u32, Error> {
+    let value = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None if text.starts_with('0') => u32::from_str_radix(text, 8).ok(),
+        None => text.parse().ok().filter(|&num: &u32| num <= 1 << 31),
+    };
+    value.ok_or_else(|| Error {
+        kind: ErrorKind::IntTooLarge(text.into()),
+        span,
+    })
+}
+
+/// Converts a hexadecimal floating-point literal (e.g. `0x1.8p3`) into `f64`.
+pub fn hex_float(text: &str) -> f64 {
+    let (mantissa, exp) = text[2..].split_once(['p', 'P']).unwrap();
+    let mut value = 0.0;
+    // saturate out-of-range exponents, the value becomes zero or infinity
+    let saturated = if exp.starts_with('-') { i32::MIN } else { i32::MAX };
+    let mut exp = exp.parse().unwrap_or(saturated).clamp(-100_000, 100_000);
+    let mut frac = false;
+    for c in mantissa.chars() {
+        match c.to_digit(16) {
+            Some(digit) => {
+                value = value * 16.0 + digit as f64;
+                if frac {
+                    exp -= 4;
+                }
+            }
+            None => frac = true,
+        }
+    }
+    if value == 0.0 {
+        0.0
+    } else {
+        value * 2f64.powi(exp)
+    }
+}
+
+/// Formats the expected token list.
+fn fmt_expected(f: &mut fmt::Formatter, expected: &[String]) -> fmt::Result {
+    match expected {
+        [] => Ok(()),
+        [e] => write!(f, ", expected {}", e),
+        [init @ .., last] => write!(f, ", expected one of {} or {}", init.join(", "), last),
+    }
+}
@@ -0,0 +1,554 @@
+use crate::ast::*;
+
+/// Pretty-printer which prints ASTs back into SysY source.
+///
+/// Comments are not part of the AST, so they are not preserved.
+/// Parentheses are only printed where they are necessary by precedence and
+/// associativity, so the printed source parses into the same AST as the
+/// original one, except for spans and redundant parentheses.
+pub struct Printer {
+    /// String of one indentation level.
+    indent: String,
+    level: usize,
+    out: String,
+    /// Lowest precedence that the expression being printed can have
+    /// without parentheses.
+    prec: Prec,
+}
+
+impl Printer {
+    /// Creates a new printer, indenting by the given number of spaces.
+    pub fn new(indent: usize) -> Self {
+        Self {
+            indent: " ".repeat(indent),
+            level: 0,
+            out: String::new(),
+            prec: Prec::LOr,
+        }
+    }
+
+    /// Prints the given compile unit, consumes the printer.
+    pub fn print(mut self, comp_unit: &CompUnit) -> String {
+        comp_unit.print(&mut self);
+        self.out
+    }
+
+    /// Starts a new line at the current indentation level.
+    fn line(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        for _ in 0..self.level {
+            self.out.push_str(&self.indent);
+        }
+    }
+
+    fn push(&mut self, s: &str) {
+        self.out.push_str(s);
+    }
+
+    /// Prints the items separated by `", "`.
+    fn list<T: Print>(&mut self, items: &[T]) {
+        for (i, item) in items.iter().enumerate() {
+            if i != 0 {
+                self.push(", ");
+            }
+            item.print(self);
+        }
+    }
+
+    /// Prints the operand of an operator, which needs parentheses if its
+    /// precedence is lower than the given one.
+    fn operand<T: Print>(&mut self, exp: &T, prec: Prec) {
+        self.prec = prec;
+        exp.print(self);
+    }
+
+    /// Prints the array dimensions or indices.
+    fn dims<T: Print>(&mut self, dims: &[T]) {
+        for dim in dims {
+            self.push("[");
+            dim.print(self);
+            self.push("]");
+        }
+    }
+
+    /// Prints the statement as the body of `if`, `else` or `while`.
+    /// Blocks stay on the same line, other statements are indented.
+    /// Statements are wrapped in braces if `braced` is `true`.
+    fn body(&mut self, stmt: &Stmt, braced: bool) {
+        if let Stmt::Block(block) = stmt {
+            self.push(" ");
+            block.print(self);
+        } else if braced {
+            self.push(" {");
+            self.level += 1;
+            self.line();
+            stmt.print(self);
+            self.level -= 1;
+            self.line();
+            self.push("}");
+        } else {
+            self.level += 1;
+            self.line();
+            stmt.print(self);
+            self.level -= 1;
+        }
+    }
+}
+
+/// Precedence of expressions, from the lowest to the highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    LOr,
+    LAnd,
+    Eq,
+    Rel,
+    Add,
+    Mul,
+    /// Unary and primary expressions.
+    Unary,
+}
+
+/// Trait for printing AST nodes.
+trait Print {
+    fn print(&self, p: &mut Printer);
+}
+
+impl<T: Print> Print for Box<T> {
+    fn print(&self, p: &mut Printer) {
+        (**self).print(p)
+    }
+}
+
+impl Print for BType {
+    fn print(&self, p: &mut Printer) {
+        p.push(match self {
+            Self::Int => "int",
+            Self::Float => "float",
+        })
+    }
+}
+
+impl Print for FuncType {
+    fn print(&self, p: &mut Printer) {
+        p.push(match self {
+            Self::Void => "void",
+            Self::Int => "int",
+            Self::Float => "float",
+        })
+    }
+}
+
+impl Print for CompUnit {
+    fn print(&self, p: &mut Printer) {
+        for (i, item) in self.items.iter().enumerate() {
+            // separate functions from other items by a blank line
+            let prev_is_func = i > 0 && matches!(self.items[i - 1], GlobalItem::FuncDef(_));
+            if i > 0 && (prev_is_func || matches!(item, GlobalItem::FuncDef(_))) {
+                p.push("\n");
+            }
+            p.line();
+            item.print(p);
+        }
+        p.push("\n");
+    }
+}
+
+impl Print for GlobalItem {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Decl(decl) => decl.print(p),
+            Self::FuncDecl(decl) => decl.print(p),
+            Self::FuncDef(def) => def.print(p),
+        }
+    }
+}
+
+impl Print for FuncDecl {
+    fn print(&self, p: &mut Printer) {
+        self.func_type.print(p);
+        p.push(" ");
+        p.push(&self.ident);
+        p.push("(");
+        p.list(&self.params);
+        p.push(");");
+    }
+}
+
+impl Print for FuncDef {
+    fn print(&self, p: &mut Printer) {
+        self.func_type.print(p);
+        p.push(" ");
+        p.push(&self.ident);
+        p.push("(");
+        p.list(&self.params);
+        p.push(") ");
+        self.block.print(p);
+    }
+}
+
+impl Print for FuncFParam {
+    fn print(&self, p: &mut Printer) {
+        self.ty.print(p);
+        p.push(" ");
+        p.push(&self.id);
+        if let Some(dims) = &self.dims {
+            p.push("[]");
+            p.dims(dims);
+        }
+    }
+}
+
+impl Print for Block {
+    fn print(&self, p: &mut Printer) {
+        p.push("{");
+        p.level += 1;
+        for item in &self.items {
+            p.line();
+            item.print(p);
+        }
+        p.level -= 1;
+        if !self.items.is_empty() {
+            p.line();
+        }
+        p.push("}");
+    }
+}
+
+impl Print for BlockItem {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Decl(decl) => decl.print(p),
+            Self::Stmt(stmt) => stmt.print(p),
+        }
+    }
+}
+
+impl Print for Stmt {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Assign(s) => {
+                s.lval.print(p);
+                p.push(" = ");
+                s.exp.print(p);
+                p.push(";");
+            }
+            Self::Return(s) => {
+                p.push("return");
+                if let Some(exp) = &s.exp {
+                    p.push(" ");
+                    exp.print(p);
+                }
+                p.push(";");
+            }
+            Self::ExpStmt(s) => {
+                if let Some(exp) = &s.exp {
+                    exp.print(p);
+                }
+                p.push(";");
+            }
+            Self::Block(block) => block.print(p),
+            Self::If(s) => {
+                p.push("if (");
+                s.cond.print(p);
+                p.push(")");
+                // the `else` would belong to the inner `if` without braces
+                let braced = s.else_then.is_some() && is_dangling(&s.then);
+                p.body(&s.then, braced);
+                if let Some(else_then) = &s.else_then {
+                    if braced || matches!(s.then, Stmt::Block(_)) {
+                        p.push(" ");
+                    } else {
+                        p.line();
+                    }
+                    p.push("else");
+                    match else_then {
+                        // keep `else if` chains flat
+                        Stmt::If(_) => {
+                            p.push(" ");
+                            else_then.print(p);
+                        }
+                        _ => p.body(else_then, false),
+                    }
+                }
+            }
+            Self::While(s) => {
+                p.push("while (");
+                s.cond.print(p);
+                p.push(")");
+                p.body(&s.body, false);
+            }
+            Self::Break(_) => p.push("break;"),
+            Self::Continue(_) => p.push("continue;"),
+        }
+    }
+}
+
+impl Print for Decl {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Const(decl) => {
+                p.push("const ");
+                decl.ty.print(p);
+                p.push(" ");
+                p.list(&decl.defs);
+            }
+            Self::Var(decl) => {
+                decl.ty.print(p);
+                p.push(" ");
+                p.list(&decl.defs);
+            }
+        }
+        p.push(";");
+    }
+}
+
+impl Print for ConstDef {
+    fn print(&self, p: &mut Printer) {
+        p.push(&self.id);
+        p.dims(&self.dims);
+        p.push(" = ");
+        self.init.print(p);
+    }
+}
+
+impl Print for ConstInitVal {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Exp(exp) => exp.print(p),
+            Self::List(inits, _) => {
+                p.push("{");
+                p.list(inits);
+                p.push("}");
+            }
+        }
+    }
+}
+
+impl Print for VarDef {
+    fn print(&self, p: &mut Printer) {
+        p.push(&self.id);
+        p.dims(&self.dims);
+        if let Some(init) = &self.init {
+            p.push(" = ");
+            init.print(p);
+        }
+    }
+}
+
+impl Print for InitVal {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Exp(exp) => exp.print(p),
+            Self::List(inits, _) => {
+                p.push("{");
+                p.list(inits);
+                p.push("}");
+            }
+        }
+    }
+}
+
+impl Print for LVal {
+    fn print(&self, p: &mut Printer) {
+        p.push(&self.id);
+        p.dims(&self.indices);
+    }
+}
+
+impl Print for ConstExp {
+    fn print(&self, p: &mut Printer) {
+        self.exp.print(p);
+    }
+}
+
+impl Print for Exp {
+    fn print(&self, p: &mut Printer) {
+        p.prec = Prec::LOr;
+        self.lor.print(p);
+    }
+}
+
+impl Print for LOrExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::LAnd(exp) => exp.print(p),
+            Self::LOrLAnd(lhs, rhs) => {
+                p.operand(lhs, Prec::LOr);
+                p.push(" || ");
+                p.operand(rhs, Prec::LAnd);
+            }
+        }
+    }
+}
+
+impl Print for LAndExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Eq(exp) => exp.print(p),
+            Self::LAndEq(lhs, rhs) => {
+                p.operand(lhs, Prec::LAnd);
+                p.push(" && ");
+                p.operand(rhs, Prec::Eq);
+            }
+        }
+    }
+}
+
+impl Print for EqExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Rel(exp) => exp.print(p),
+            Self::EqRel(lhs, op, rhs) => {
+                p.operand(lhs, Prec::Eq);
+                p.push(match op {
+                    EqOp::Eq => " == ",
+                    EqOp::Neq => " != ",
+                });
+                p.operand(rhs, Prec::Rel);
+            }
+        }
+    }
+}
+
+impl Print for RelExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Add(exp) => exp.print(p),
+            Self::RelAdd(lhs, op, rhs) => {
+                p.operand(lhs, Prec::Rel);
+                p.push(match op {
+                    RelOp::Lt => " < ",
+                    RelOp::Gt => " > ",
+                    RelOp::Le => " <= ",
+                    RelOp::Ge => " >= ",
+                });
+                p.operand(rhs, Prec::Add);
+            }
+        }
+    }
+}
+
+impl Print for AddExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Mul(exp) => exp.print(p),
+            Self::AddMul(lhs, op, rhs) => {
+                p.operand(lhs, Prec::Add);
+                p.push(match op {
+                    AddOp::Add => " + ",
+                    AddOp::Sub => " - ",
+                });
+                p.operand(rhs, Prec::Mul);
+            }
+        }
+    }
+}
+
+impl Print for MulExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Unary(exp) => exp.print(p),
+            Self::MulUnary(lhs, op, rhs) => {
+                p.operand(lhs, Prec::Mul);
+                p.push(match op {
+                    MulOp::Mul => " * ",
+                    MulOp::Div => " / ",
+                    MulOp::Mod => " % ",
+                });
+                p.operand(rhs, Prec::Unary);
+            }
+        }
+    }
+}
+
+impl Print for UnaryExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            Self::Primary(exp) => exp.print(p),
+            Self::Call(call) => call.print(p),
+            Self::Unary(op, exp, _) => {
+                p.push(match op {
+                    UnaryOp::Neg => "-",
+                    UnaryOp::LNot => "!",
+                });
+                let start = p.out.len();
+                p.operand(exp, Prec::Unary);
+                // print `- -x` instead of `--x`
+                if let (UnaryOp::Neg, Some('-')) = (op, p.out[start..].chars().next()) {
+                    p.out.insert(start, ' ');
+                }
+            }
+        }
+    }
+}
+
+impl Print for FuncCall {
+    fn print(&self, p: &mut Printer) {
+        p.push(&self.id);
+        p.push("(");
+        p.list(&self.args);
+        p.push(")");
+    }
+}
+
+impl Print for PrimaryExp {
+    fn print(&self, p: &mut Printer) {
+        match self {
+            // the expression takes the place of this one without parentheses,
+            // so its operands are printed by the same precedence
+            Self::Exp(exp) if prec(exp) >= p.prec => exp.lor.print(p),
+            Self::Exp(exp) => {
+                p.push("(");
+                exp.print(p);
+                p.push(")");
+            }
+            Self::LVal(lval) => lval.print(p),
+            Self::Number(num, _) => p.push(&int_literal(*num)),
+            Self::Float(_, text, _) => p.push(text),
+        }
+    }
+}
+
+/// Returns `true` if the statement ends with an `if` without `else`.
+fn is_dangling(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::If(s) => s.else_then.as_ref().is_none_or(is_dangling),
+        Stmt::While(s) => is_dangling(&s.body),
+        _ => false,
+    }
+}
+
+/// Returns the precedence of the operator at the top level of the expression.
+fn prec(exp: &Exp) -> Prec {
+    let LOrExp::LAnd(exp) = &exp.lor else {
+        return Prec::LOr;
+    };
+    let LAndExp::Eq(exp) = exp else {
+        return Prec::LAnd;
+    };
+    let EqExp::Rel(exp) = exp else {
+        return Prec::Eq;
+    };
+    let RelExp::Add(exp) = exp else {
+        return Prec::Rel;
+    };
+    let AddExp::Mul(exp) = exp else {
+        return Prec::Add;
+    };
+    match exp {
+        MulExp::Unary(_) => Prec::Unary,
+        MulExp::MulUnary(..) => Prec::Mul,
+    }
+}
+
+/// Returns an integer literal which is parsed back into the given integer,
+/// decimal literals can not be greater than 2^31.
+fn int_literal(num: u32) -> String {
+    if num <= 1 << 31 {
+        num.to_string()
+    } else {
+        format!("{:#x}", num)
+    }
+}
@@ -0,0 +1,880 @@
+use super::flow::Flow;
+use super::fold;
+use super::init::{element, reshape};
+use super::scopes::Scopes;
+use super::tree::{self, BinaryOp, Const, Exp, ExpKind, Init, Signature, Stmt};
+use super::tree::{SymbolId, SymbolKind, Ty};
+use super::{AtSpan, Error, ErrorKind, Result, WarningKind};
+use crate::ast::{self, BType, FuncType, Span, Spanned, UnaryOp};
+
+/// Trait for analyzing AST nodes.
+pub trait Analyze<'ast> {
+    type Out;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out>;
+}
+
+/// Global item whose function body is analyzed after all signatures.
+enum Pending<'ast> {
+    Decls(Vec<tree::VarDef>),
+    /// Function definition, with its symbol, parameter types, and the
+    /// number of symbols visible in global scope at the definition.
+    Func(&'ast ast::FuncDef, SymbolId, Vec<Ty>, usize),
+}
+
+impl<'ast> Analyze<'ast> for ast::CompUnit {
+    type Out = Vec<tree::GlobalItem>;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        // declare SysY library functions
+        let int_ptr = || Ty::Pointer(Box::new(Ty::Int));
+        let float_ptr = || Ty::Pointer(Box::new(Ty::Float));
+        let library = [
+            ("getint", vec![], FuncType::Int),
+            ("getch", vec![], FuncType::Int),
+            ("getfloat", vec![], FuncType::Float),
+            ("getarray", vec![int_ptr()], FuncType::Int),
+            ("getfarray", vec![float_ptr()], FuncType::Int),
+            ("putint", vec![Ty::Int], FuncType::Void),
+            ("putch", vec![Ty::Int], FuncType::Void),
+            ("putfloat", vec![Ty::Float], FuncType::Void),
+            ("putarray", vec![Ty::Int, int_ptr()], FuncType::Void),
+            ("putfarray", vec![Ty::Int, float_ptr()], FuncType::Void),
+            ("starttime", vec![], FuncType::Void),
+            ("stoptime", vec![], FuncType::Void),
+        ];
+        for (name, params, ret) in library {
+            let sig = Signature { params, ret };
+            scopes.declare_func(name, sig, Span::default(), true).unwrap();
+        }
+        // analyze global declarations and function signatures in order,
+        // so that functions can be called before their definitions
+        let mut pending = Vec::new();
+        for item in &self.items {
+            match item {
+                ast::GlobalItem::Decl(decl) => pending.push(Pending::Decls(decl.analyze(scopes)?)),
+                ast::GlobalItem::FuncDecl(decl) => {
+                    if let Err(err) = decl.analyze(scopes) {
+                        scopes.report(err.kind, err.span);
+                    }
+                }
+                ast::GlobalItem::FuncDef(def) => match declare_def(def, scopes) {
+                    Ok((id, params)) => {
+                        let visible = scopes.symbol_count();
+                        pending.push(Pending::Func(def, id, params, visible));
+                    }
+                    Err(err) => scopes.report(err.kind, err.span),
+                },
+            }
+        }
+        // analyze function bodies, which only see global declarations before them
+        let mut items = Vec::new();
+        for item in pending {
+            match item {
+                Pending::Decls(defs) => items.extend(defs.into_iter().map(tree::GlobalItem::Decl)),
+                Pending::Func(def, id, params, visible) => {
+                    scopes.visible = visible;
+                    match func_body(def, id, params, scopes) {
+                        Ok(def) => items.push(tree::GlobalItem::FuncDef(def)),
+                        Err(err) => scopes.report(err.kind, err.span),
+                    }
+                }
+            }
+        }
+        scopes.visible = usize::MAX;
+        // check functions that are called but never defined
+        for id in scopes.undefined_funcs() {
+            if scopes.is_used(id) {
+                let symbol = scopes.symbol(id);
+                let kind = ErrorKind::UndefinedFunc(symbol.name.clone());
+                scopes.report(kind, symbol.span);
+            }
+        }
+        // warn functions that are never called, except `main`
+        for item in &items {
+            if let tree::GlobalItem::FuncDef(def) = item {
+                let symbol = scopes.symbol(def.id);
+                if symbol.name != "main" && !scopes.is_used(def.id) {
+                    let kind = WarningKind::UnusedFunc(symbol.name.clone());
+                    scopes.warn(kind, symbol.span);
+                }
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// Returns the signature of a function with the given return type
+/// and parameters.
+fn signature<'ast>(
+    ret: FuncType,
+    params: &'ast [ast::FuncFParam],
+    scopes: &mut Scopes<'ast>,
+) -> Result<Signature> {
+    let params = params
+        .iter()
+        .map(|p| p.analyze(scopes))
+        .collect::<Result<_>>()?;
+    Ok(Signature { params, ret })
+}
+
+impl<'ast> Analyze<'ast> for ast::FuncDecl {
+    type Out = SymbolId;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        let sig = signature(self.func_type, &self.params, scopes)?;
+        scopes
+            .declare_func(&self.ident, sig, self.span, false)
+            .at(self.span)
+    }
+}
+
+/// Defines the function, returns its symbol and parameter types.
+fn declare_def<'ast>(
+    def: &'ast ast::FuncDef,
+    scopes: &mut Scopes<'ast>,
+) -> Result<(SymbolId, Vec<Ty>)> {
+    let sig = signature(def.func_type, &def.params, scopes)?;
+    let params = sig.params.clone();
+    let id = match scopes.declare_func(&def.ident, sig.clone(), def.span, true) {
+        Ok(id) => id,
+        Err(kind) => {
+            // analyze the body anyway
+            scopes.report(kind, def.span);
+            scopes.new_symbol(&def.ident, SymbolKind::Func(sig), def.span)
+        }
+    };
+    Ok((id, params))
+}
+
+/// Analyzes the body of the defined function.
+fn func_body<'ast>(
+    def: &'ast ast::FuncDef,
+    id: SymbolId,
+    params_ty: Vec<Ty>,
+    scopes: &mut Scopes<'ast>,
+) -> Result<tree::FuncDef> {
+    // define parameters and analyze the body
+    let global_flow = scopes.flow.clone();
+    scopes.enter();
+    scopes.cur_ret = Some(def.func_type);
+    let mut params = Vec::new();
+    for (param, ty) in def.params.iter().zip(params_ty) {
+        let kind = SymbolKind::Var(ty.clone());
+        params.push(match scopes.new_value(&param.id, kind, param.span) {
+            Ok(id) => id,
+            Err(kind) => {
+                scopes.report(kind, param.span);
+                scopes.new_symbol(&param.id, SymbolKind::Var(ty), param.span)
+            }
+        });
+        scopes.flow.init(*params.last().unwrap());
+    }
+    let block = def.block.analyze(scopes);
+    // point at the closing brace if the end is reachable
+    if def.func_type != FuncType::Void && !scopes.flow.is_diverged() {
+        let span = Span::new(def.block.span.end - 1, def.block.span.end);
+        scopes.warn(WarningKind::MissingReturn(def.ident.clone()), span);
+    }
+    scopes.flow = global_flow;
+    scopes.cur_ret = None;
+    scopes.exit();
+    Ok(tree::FuncDef {
+        id,
+        params,
+        block: block?,
+    })
+}
+
+impl<'ast> Analyze<'ast> for ast::FuncFParam {
+    type Out = Ty;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        Ok(match &self.dims {
+            Some(dims) => Ty::Pointer(Box::new(array_ty(dims, self.ty, scopes)?)),
+            None => self.ty.into(),
+        })
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::Block {
+    type Out = tree::Block;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        scopes.enter();
+        let mut stmts = Vec::new();
+        // warn only the first unreachable item, in the outermost block
+        let mut warned = scopes.flow.is_diverged();
+        for item in &self.items {
+            if !warned && scopes.flow.is_diverged() {
+                scopes.warn(WarningKind::Unreachable, item.span());
+                warned = true;
+            }
+            match item {
+                ast::BlockItem::Decl(decl) => match decl.analyze(scopes) {
+                    Ok(defs) => stmts.extend(defs.into_iter().map(Stmt::Decl)),
+                    Err(err) => scopes.report(err.kind, err.span),
+                },
+                ast::BlockItem::Stmt(stmt) => match stmt.analyze(scopes) {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(err) => scopes.report(err.kind, err.span),
+                },
+            }
+        }
+        scopes.exit();
+        Ok(tree::Block { stmts })
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::Decl {
+    type Out = Vec<tree::VarDef>;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Const(c) => c.analyze(scopes),
+            Self::Var(v) => v.analyze(scopes),
+        }
+    }
+}
+
+/// Reports the error in a definition, and defines the symbol anyway
+/// to avoid errors on its uses.
+fn report_def<'ast>(scopes: &mut Scopes<'ast>, id: &'ast str, span: Span, err: Error) {
+    scopes.report(err.kind, err.span);
+    let _ = scopes.new_value(id, SymbolKind::Var(Ty::Error), span);
+}
+
+/// Returns the type of an array of the given base type and dimensions.
+fn array_ty<'ast>(
+    dims: &'ast [ast::ConstExp],
+    base: BType,
+    scopes: &mut Scopes<'ast>,
+) -> Result<Ty> {
+    dims.iter()
+        .rev()
+        .try_fold(base.into(), |ty, exp| match exp.analyze(scopes)? {
+            Const::Int(len) if len >= 1 => Ok(Ty::Array(Box::new(ty), len as usize)),
+            _ => Err(ErrorKind::InvalidArrayLen).at(exp.span),
+        })
+}
+
+impl<'ast> Analyze<'ast> for ast::VarDecl {
+    type Out = Vec<tree::VarDef>;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        scopes.decl_ty = self.ty;
+        let mut defs = Vec::new();
+        for def in &self.defs {
+            match def.analyze(scopes) {
+                Ok(d) => defs.push(d),
+                Err(err) => report_def(scopes, &def.id, def.span, err),
+            }
+        }
+        Ok(defs)
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::VarDef {
+    type Out = tree::VarDef;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        // analyze type and initializer
+        let ty = array_ty(&self.dims, scopes.decl_ty, scopes)?;
+        let init = match &self.init {
+            Some(init) => Some(reshape(init.analyze(scopes)?, &ty).at(init.span())?),
+            None => None,
+        };
+        // add to scope
+        let id = scopes
+            .new_value(&self.id, SymbolKind::Var(ty), self.span)
+            .at(self.span)?;
+        if init.is_some() || scopes.is_global() {
+            scopes.flow.init(id);
+        }
+        Ok(tree::VarDef { id, init })
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::InitVal {
+    type Out = Init;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        Ok(match self {
+            Self::Exp(exp) => {
+                let exp = cast(rvalue(exp.analyze(scopes)?)?, scopes.decl_ty);
+                // initializers of global variables must be constant
+                if scopes.is_global() && exp.as_const().is_none() && exp.ty != Ty::Error {
+                    return Err(ErrorKind::FailedToEval).at(exp.span);
+                }
+                Init::Exp(exp)
+            }
+            Self::List(exps, _) => Init::List(
+                exps.iter()
+                    .map(|v| v.analyze(scopes))
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::ConstDecl {
+    type Out = Vec<tree::VarDef>;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        scopes.decl_ty = self.ty;
+        let mut defs = Vec::new();
+        for def in &self.defs {
+            match def.analyze(scopes) {
+                Ok(d) => defs.extend(d),
+                Err(err) => report_def(scopes, &def.id, def.span, err),
+            }
+        }
+        Ok(defs)
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::ConstDef {
+    /// Constant arrays are defined like variables, constant numbers
+    /// are folded into their uses.
+    type Out = Option<tree::VarDef>;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        // analyze type and initializer
+        let ty = array_ty(&self.dims, scopes.decl_ty, scopes)?;
+        let init = reshape(self.init.analyze(scopes)?, &ty).at(self.init.span())?;
+        // add to scope
+        match init {
+            Init::Exp(exp) => {
+                let kind = SymbolKind::Const(exp.as_const().unwrap());
+                scopes.new_value(&self.id, kind, self.span).at(self.span)?;
+                Ok(None)
+            }
+            init => {
+                let kind = SymbolKind::ConstArray(ty, init.clone());
+                let id = scopes.new_value(&self.id, kind, self.span).at(self.span)?;
+                Ok(Some(tree::VarDef {
+                    id,
+                    init: Some(init),
+                }))
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::ConstInitVal {
+    type Out = Init;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        Ok(match self {
+            Self::Exp(exp) => {
+                let num = exp.analyze(scopes)?.cast(scopes.decl_ty);
+                Init::Exp(Exp::constant(num, exp.span))
+            }
+            Self::List(list, _) => Init::List(
+                list.iter()
+                    .map(|v| v.analyze(scopes))
+                    .collect::<Result<_>>()?,
+            ),
+        })
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::ConstExp {
+    type Out = Const;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        let exp = self.exp.analyze(scopes)?;
+        match exp.kind {
+            ExpKind::Const(num) => Ok(num),
+            // errors have been reported, any valid constant works
+            ExpKind::Error => Ok(Const::Int(1)),
+            _ => Err(ErrorKind::FailedToEval).at(self.span),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::Stmt {
+    type Out = Stmt;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        let result = match self {
+            Self::Assign(a) => a.analyze(scopes),
+            Self::Return(s) => s.analyze(scopes),
+            Self::ExpStmt(e) => e.analyze(scopes),
+            Self::Block(b) => b.analyze(scopes).map(Stmt::Block),
+            Self::If(s) => s.analyze(scopes),
+            Self::While(s) => s.analyze(scopes),
+            Self::Break(_) | Self::Continue(_) if scopes.loops.is_empty() => {
+                Err(ErrorKind::NotInLoop).at(self.span())
+            }
+            Self::Break(_) => {
+                let flow = scopes.flow.clone();
+                scopes.loops.last_mut().unwrap().merge(flow);
+                scopes.flow.diverge();
+                Ok(Stmt::Break)
+            }
+            Self::Continue(_) => {
+                scopes.flow.diverge();
+                Ok(Stmt::Continue)
+            }
+        };
+        // report the error and continue with the next statement
+        Ok(result.unwrap_or_else(|err| {
+            scopes.report(err.kind, err.span);
+            Stmt::Exp(None)
+        }))
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::While {
+    type Out = Stmt;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        let cond = rvalue(self.cond.analyze(scopes)?)?;
+        // the loop exits if the condition is false, or at `break`s
+        let mut exit = if cond.as_const().is_some_and(|c| c.is_true()) {
+            Flow::diverged()
+        } else {
+            scopes.flow.clone()
+        };
+        scopes.loops.push(Flow::diverged());
+        let body = self.body.analyze(scopes);
+        exit.merge(scopes.loops.pop().unwrap());
+        scopes.flow = exit;
+        Ok(Stmt::While(cond, Box::new(body?)))
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::If {
+    type Out = Stmt;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        let cond = rvalue(self.cond.analyze(scopes)?)?;
+        let before = scopes.flow.clone();
+        let then = self.then.analyze(scopes);
+        let then_flow = std::mem::replace(&mut scopes.flow, before);
+        let else_then = match &self.else_then {
+            Some(else_then) => Some(Box::new(else_then.analyze(scopes)?)),
+            None => None,
+        };
+        scopes.flow.merge(then_flow);
+        Ok(Stmt::If(cond, Box::new(then?), else_then))
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::ExpStmt {
+    type Out = Stmt;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match &self.exp {
+            Some(exp) => Ok(Stmt::Exp(Some(exp.analyze(scopes)?))),
+            None => Ok(Stmt::Exp(None)),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::Assign {
+    type Out = Stmt;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        // check if assigning to constant
+        if let Ok(id) = scopes.value(&self.lval.id) {
+            if let SymbolKind::Const(_) | SymbolKind::ConstArray(..) = scopes.symbol(id).kind {
+                return Err(ErrorKind::AssignToConst(self.lval.id.clone())).at(self.lval.span);
+            }
+        }
+        // analyze value and left value, assigning to a variable
+        // initializes it but does not use it
+        let exp = rvalue(self.exp.analyze(scopes)?)?;
+        let lval = lval(&self.lval, scopes, !self.lval.indices.is_empty())?;
+        if let ExpKind::LVal(id, indices) = &lval.kind {
+            if indices.is_empty() {
+                scopes.flow.init(*id);
+            }
+        }
+        match lval.ty.base_type() {
+            Some(ty) => Ok(Stmt::Assign(lval, cast(exp, ty))),
+            None if lval.ty == Ty::Error => Ok(Stmt::Exp(None)),
+            None => Err(ErrorKind::ArrayAssign(self.lval.id.clone())).at(self.lval.span),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::Return {
+    type Out = Stmt;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        let result = match (scopes.cur_ret.unwrap().base_type(), &self.exp) {
+            (Some(ty), Some(exp)) => {
+                let exp = cast(rvalue(exp.analyze(scopes)?)?, ty);
+                Ok(Stmt::Return(Some(exp)))
+            }
+            (None, Some(_)) => Err(ErrorKind::RetValInVoidFunc).at(self.span),
+            (Some(_), None) => Err(ErrorKind::NoRetValInNonVoidFunc).at(self.span),
+            (None, None) => Ok(Stmt::Return(None)),
+        };
+        scopes.flow.diverge();
+        result
+    }
+}
+
+/// Checks if the expression is an integer or a float.
+fn rvalue(exp: Exp) -> Result<Exp> {
+    match exp.ty {
+        Ty::Void => Err(ErrorKind::UseVoidValue).at(exp.span),
+        Ty::Array(..) | Ty::Pointer(_) => Err(ErrorKind::NonIntCalc).at(exp.span),
+        _ => Ok(exp),
+    }
+}
+
+/// Converts the integer or float expression to the given type.
+fn cast(exp: Exp, ty: BType) -> Exp {
+    match exp.ty.base_type() {
+        Some(from) if from != ty => match exp.as_const() {
+            Some(num) => Exp::constant(num.cast(ty), exp.span),
+            None => Exp {
+                span: exp.span,
+                kind: ExpKind::Cast(Box::new(exp)),
+                ty: ty.into(),
+            },
+        },
+        _ => exp,
+    }
+}
+
+/// Creates a binary operation, operands are converted to float
+/// if either of them is a float. Constant operands are folded.
+fn binary(scopes: &mut Scopes, op: BinaryOp, lhs: Exp, rhs: Exp, span: Span) -> Result<Exp> {
+    let (lhs, rhs) = (rvalue(lhs)?, rvalue(rhs)?);
+    let ty = match (&lhs.ty, &rhs.ty) {
+        (Ty::Error, _) | (_, Ty::Error) => return Ok(Exp::error(span)),
+        (Ty::Float, _) | (_, Ty::Float) if op == BinaryOp::Mod => {
+            return Err(ErrorKind::NonIntCalc).at(span);
+        }
+        (Ty::Float, _) | (_, Ty::Float) => BType::Float,
+        _ => BType::Int,
+    };
+    let (lhs, rhs) = (cast(lhs, ty), cast(rhs, ty));
+    if let (Some(l), Some(r)) = (lhs.as_const(), rhs.as_const()) {
+        return Ok(Exp::constant(fold::binary(scopes, span, op, l, r), span));
+    }
+    Ok(Exp {
+        kind: ExpKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+        ty: if op.is_compare() { Ty::Int } else { ty.into() },
+        span,
+    })
+}
+
+/// Creates a logical operation, `is_and` for `&&`, otherwise `||`.
+fn logical(lhs: Exp, rhs: Exp, is_and: bool, span: Span) -> Result<Exp> {
+    let (lhs, rhs) = (rvalue(lhs)?, rvalue(rhs)?);
+    if lhs.ty == Ty::Error || rhs.ty == Ty::Error {
+        return Ok(Exp::error(span));
+    }
+    let kind = match (lhs.as_const(), rhs.as_const()) {
+        (Some(l), Some(r)) if is_and => Const::Int((l.is_true() && r.is_true()) as i32).into(),
+        (Some(l), Some(r)) => Const::Int((l.is_true() || r.is_true()) as i32).into(),
+        _ if is_and => ExpKind::LAnd(Box::new(lhs), Box::new(rhs)),
+        _ => ExpKind::LOr(Box::new(lhs), Box::new(rhs)),
+    };
+    Ok(Exp {
+        kind,
+        ty: Ty::Int,
+        span,
+    })
+}
+
+impl From<Const> for ExpKind {
+    fn from(num: Const) -> Self {
+        Self::Const(num)
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::Exp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        self.lor.analyze(scopes)
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::LOrExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::LAnd(exp) => exp.analyze(scopes),
+            Self::LOrLAnd(lhs, rhs) => {
+                let span = lhs.span().to(rhs.span());
+                logical(lhs.analyze(scopes)?, rhs.analyze(scopes)?, false, span)
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::LAndExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Eq(exp) => exp.analyze(scopes),
+            Self::LAndEq(lhs, rhs) => {
+                let span = lhs.span().to(rhs.span());
+                logical(lhs.analyze(scopes)?, rhs.analyze(scopes)?, true, span)
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::EqExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Rel(exp) => exp.analyze(scopes),
+            Self::EqRel(lhs, op, rhs) => {
+                let span = lhs.span().to(rhs.span());
+                let op = match op {
+                    ast::EqOp::Eq => BinaryOp::Eq,
+                    ast::EqOp::Neq => BinaryOp::NotEq,
+                };
+                let (lhs, rhs) = (lhs.analyze(scopes)?, rhs.analyze(scopes)?);
+                binary(scopes, op, lhs, rhs, span)
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::RelExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Add(exp) => exp.analyze(scopes),
+            Self::RelAdd(lhs, op, rhs) => {
+                let span = lhs.span().to(rhs.span());
+                let op = match op {
+                    ast::RelOp::Lt => BinaryOp::Lt,
+                    ast::RelOp::Gt => BinaryOp::Gt,
+                    ast::RelOp::Le => BinaryOp::Le,
+                    ast::RelOp::Ge => BinaryOp::Ge,
+                };
+                let (lhs, rhs) = (lhs.analyze(scopes)?, rhs.analyze(scopes)?);
+                binary(scopes, op, lhs, rhs, span)
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::AddExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Mul(exp) => exp.analyze(scopes),
+            Self::AddMul(lhs, op, rhs) => {
+                let span = lhs.span().to(rhs.span());
+                let op = match op {
+                    ast::AddOp::Add => BinaryOp::Add,
+                    ast::AddOp::Sub => BinaryOp::Sub,
+                };
+                let (lhs, rhs) = (lhs.analyze(scopes)?, rhs.analyze(scopes)?);
+                binary(scopes, op, lhs, rhs, span)
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::MulExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Unary(exp) => exp.analyze(scopes),
+            Self::MulUnary(lhs, op, rhs) => {
+                let span = lhs.span().to(rhs.span());
+                let op = match op {
+                    ast::MulOp::Mul => BinaryOp::Mul,
+                    ast::MulOp::Div => BinaryOp::Div,
+                    ast::MulOp::Mod => BinaryOp::Mod,
+                };
+                let (lhs, rhs) = (lhs.analyze(scopes)?, rhs.analyze(scopes)?);
+                binary(scopes, op, lhs, rhs, span)
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::UnaryExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Primary(exp) => exp.analyze(scopes),
+            Self::Call(call) => call.analyze(scopes),
+            // `-2147483648`, the only literal greater than `i32::MAX`
+            // which does not overflow
+            Self::Unary(UnaryOp::Neg, exp, span)
+                if matches!(
+                    **exp,
+                    ast::UnaryExp::Primary(ast::PrimaryExp::Number(0x8000_0000, _))
+                ) =>
+            {
+                Ok(Exp::constant(Const::Int(i32::MIN), *span))
+            }
+            Self::Unary(op, exp, span) => {
+                let exp = rvalue(exp.analyze(scopes)?)?;
+                let ty = match (op, &exp.ty) {
+                    (_, Ty::Error) => return Ok(Exp::error(*span)),
+                    (UnaryOp::Neg, ty) => ty.clone(),
+                    (UnaryOp::LNot, _) => Ty::Int,
+                };
+                Ok(match exp.as_const() {
+                    Some(num) => Exp::constant(fold::unary(scopes, *span, *op, num), *span),
+                    None => Exp {
+                        kind: ExpKind::Unary(*op, Box::new(exp)),
+                        ty,
+                        span: *span,
+                    },
+                })
+            }
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::PrimaryExp {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        match self {
+            Self::Exp(exp) => exp.analyze(scopes),
+            Self::LVal(lval) => lval.analyze(scopes),
+            Self::Number(num, span) => {
+                let num = *num as i32;
+                if num < 0 {
+                    scopes.warn(WarningKind::LiteralOverflow(num), *span);
+                }
+                Ok(Exp::constant(Const::Int(num), *span))
+            }
+            Self::Float(num, _, span) => Ok(Exp::constant(Const::Float(*num), *span)),
+        }
+    }
+}
+
+impl<'ast> Analyze<'ast> for ast::LVal {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        lval(self, scopes, true)
+    }
+}
+
+/// Analyzes the left value, `read` is `false` if it is only assigned to.
+fn lval<'ast>(lval: &'ast ast::LVal, scopes: &mut Scopes<'ast>, read: bool) -> Result<Exp> {
+    let id = match scopes.value(&lval.id) {
+        Ok(id) => id,
+        Err(kind) => {
+            scopes.report(kind, lval.span);
+            return Ok(Exp::error(lval.span));
+        }
+    };
+    if read {
+        scopes.use_symbol(id);
+        // warn once for each uninitialized variable on the current path
+        let is_scalar = matches!(scopes.symbol(id).kind, SymbolKind::Var(Ty::Int | Ty::Float));
+        if is_scalar && !scopes.flow.is_inited(id) && !scopes.flow.is_diverged() {
+            scopes.warn(WarningKind::Uninit(lval.id.clone()), lval.span);
+            scopes.flow.init(id);
+        }
+    }
+    let indices = lval
+        .indices
+        .iter()
+        .map(|index| Ok(cast(rvalue(index.analyze(scopes)?)?, BType::Int)))
+        .collect::<Result<Vec<_>>>()?;
+    // handle constant
+    let ty = match &scopes.symbol(id).kind {
+        SymbolKind::Var(Ty::Error) => return Ok(Exp::error(lval.span)),
+        SymbolKind::Var(ty) => ty,
+        SymbolKind::Const(num) if indices.is_empty() => {
+            return Ok(Exp::constant(*num, lval.span));
+        }
+        SymbolKind::Const(_) => {
+            return Err(ErrorKind::DerefInt(lval.id.clone())).at(lval.span);
+        }
+        SymbolKind::ConstArray(ty, init) => {
+            // fold elements of constant arrays
+            let indices = indices.iter().map(|index| match index.as_const() {
+                Some(Const::Int(index)) => Some(index),
+                _ => None,
+            });
+            match indices.collect::<Option<Vec<_>>>().and_then(|i| element(init, i)) {
+                Some(num) => return Ok(Exp::constant(num, lval.span)),
+                None => ty,
+            }
+        }
+        SymbolKind::Func(_) => unreachable!(),
+    };
+    // check if dereferencing integer
+    let mut ty = ty;
+    for _ in &indices {
+        ty = ty
+            .elem()
+            .ok_or_else(|| ErrorKind::DerefInt(lval.id.clone()))
+            .at(lval.span)?;
+    }
+    Ok(Exp {
+        ty: ty.clone(),
+        kind: ExpKind::LVal(id, indices),
+        span: lval.span,
+    })
+}
+
+impl<'ast> Analyze<'ast> for ast::FuncCall {
+    type Out = Exp;
+
+    fn analyze(&'ast self, scopes: &mut Scopes<'ast>) -> Result<Self::Out> {
+        // get function and its signature from scope
+        let (id, sig) = match scopes.func(&self.id) {
+            Ok((id, sig)) => (id, sig.clone()),
+            Err(kind) => {
+                scopes.report(kind, self.span);
+                // check arguments for more errors
+                for arg in &self.args {
+                    arg.analyze(scopes)?;
+                }
+                return Ok(Exp::error(self.span));
+            }
+        };
+        scopes.use_symbol(id);
+        let mismatch = || ErrorKind::ArgMismatch(self.id.clone());
+        if sig.params.len() != self.args.len() {
+            scopes.report(mismatch(), self.span);
+            return Ok(Exp::error(self.span));
+        }
+        // analyze arguments and check their types,
+        // integers and floats are converted to the types of parameters
+        let mut args = Vec::new();
+        let mut poisoned = false;
+        for (exp, param) in self.args.iter().zip(&sig.params) {
+            let arg = exp.analyze(scopes)?;
+            match param.base_type() {
+                Some(ty) => args.push(cast(rvalue(arg)?, ty)),
+                None if arg.ty == Ty::Error => poisoned = true,
+                None if arg.ty.decay() == *param => args.push(arg),
+                None => {
+                    scopes.report(mismatch(), exp.span);
+                    poisoned = true;
+                }
+            }
+        }
+        if poisoned || args.iter().any(|arg| arg.ty == Ty::Error) {
+            return Ok(Exp::error(self.span));
+        }
+        Ok(Exp {
+            kind: ExpKind::Call(id, args),
+            ty: sig.ret.into(),
+            span: self.span,
+        })
+    }
+}
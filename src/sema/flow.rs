@@ -0,0 +1,60 @@
+//! Control flow state for reachability and initialization warnings.
+
+use super::tree::SymbolId;
+use std::collections::HashSet;
+
+/// State of the control flow at the current statement.
+///
+/// Statements are analyzed in order, branches are analyzed from clones
+/// of the state, and the states at the end of branches are merged.
+#[derive(Debug, Clone, Default)]
+pub struct Flow {
+    /// Variables which have been initialized on all paths.
+    inited: HashSet<SymbolId>,
+    /// `true` if the current statement is unreachable.
+    diverged: bool,
+}
+
+impl Flow {
+    /// Creates the state of a path that never reaches here,
+    /// which is the identity of `merge`.
+    pub fn diverged() -> Self {
+        Self {
+            inited: HashSet::new(),
+            diverged: true,
+        }
+    }
+
+    /// Marks the variable as initialized.
+    pub fn init(&mut self, id: SymbolId) {
+        self.inited.insert(id);
+    }
+
+    /// Returns `true` if the variable has been initialized on all paths.
+    pub fn is_inited(&self, id: SymbolId) -> bool {
+        self.inited.contains(&id)
+    }
+
+    /// Marks the current statement as unreachable,
+    /// after `return`, `break` or `continue`.
+    pub fn diverge(&mut self) {
+        self.diverged = true;
+    }
+
+    /// Returns `true` if the current statement is unreachable.
+    pub fn is_diverged(&self) -> bool {
+        self.diverged
+    }
+
+    /// Merges the state of another path which joins the current one.
+    pub fn merge(&mut self, other: Flow) {
+        if other.diverged {
+            return;
+        }
+        if self.diverged {
+            *self = other;
+        } else {
+            self.inited.retain(|id| other.inited.contains(id));
+        }
+    }
+}
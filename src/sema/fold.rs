@@ -0,0 +1,70 @@
+//! Constant folding, with the same semantics as the generated code.
+
+use super::scopes::Scopes;
+use super::tree::{BinaryOp, Const, UnaryOp};
+use super::WarningKind;
+use crate::ast::Span;
+
+/// Evaluates an integer operation with the wrapping semantics of RISC-V
+/// `add`/`sub`/`mul`/`div`/`rem`, warns if it overflows or divides by zero.
+fn int_op(scopes: &mut Scopes, span: Span, op: BinaryOp, lhs: i32, rhs: i32) -> i32 {
+    let (result, overflow) = match op {
+        BinaryOp::Add => lhs.overflowing_add(rhs),
+        BinaryOp::Sub => lhs.overflowing_sub(rhs),
+        BinaryOp::Mul => lhs.overflowing_mul(rhs),
+        // division by zero does not trap in RISC-V
+        BinaryOp::Div | BinaryOp::Mod if rhs == 0 => {
+            let result = if let BinaryOp::Div = op { -1 } else { lhs };
+            scopes.warn(WarningKind::DivByZero(result), span);
+            return result;
+        }
+        BinaryOp::Div => lhs.overflowing_div(rhs),
+        BinaryOp::Mod => lhs.overflowing_rem(rhs),
+        _ => unreachable!(),
+    };
+    if overflow {
+        scopes.warn(WarningKind::Overflow(result), span);
+    }
+    result
+}
+
+/// Evaluates a comparison.
+fn compare<T: PartialOrd>(op: BinaryOp, lhs: T, rhs: T) -> Const {
+    let result = match op {
+        BinaryOp::Eq => lhs == rhs,
+        BinaryOp::NotEq => lhs != rhs,
+        BinaryOp::Lt => lhs < rhs,
+        BinaryOp::Gt => lhs > rhs,
+        BinaryOp::Le => lhs <= rhs,
+        BinaryOp::Ge => lhs >= rhs,
+        _ => unreachable!(),
+    };
+    Const::Int(result as i32)
+}
+
+/// Evaluates a binary operation, operands must have the same type,
+/// and the operation must be valid on the type.
+pub fn binary(scopes: &mut Scopes, span: Span, op: BinaryOp, lhs: Const, rhs: Const) -> Const {
+    match (lhs, rhs) {
+        (Const::Int(lhs), Const::Int(rhs)) if op.is_compare() => compare(op, lhs, rhs),
+        (Const::Int(lhs), Const::Int(rhs)) => Const::Int(int_op(scopes, span, op, lhs, rhs)),
+        (Const::Float(lhs), Const::Float(rhs)) => match op {
+            BinaryOp::Add => Const::Float(lhs + rhs),
+            BinaryOp::Sub => Const::Float(lhs - rhs),
+            BinaryOp::Mul => Const::Float(lhs * rhs),
+            BinaryOp::Div => Const::Float(lhs / rhs),
+            BinaryOp::Mod => unreachable!(),
+            _ => compare(op, lhs, rhs),
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Evaluates a unary operation.
+pub fn unary(scopes: &mut Scopes, span: Span, op: UnaryOp, exp: Const) -> Const {
+    match (op, exp) {
+        (UnaryOp::Neg, Const::Int(i)) => Const::Int(int_op(scopes, span, BinaryOp::Sub, 0, i)),
+        (UnaryOp::Neg, Const::Float(f)) => Const::Float(-f),
+        (UnaryOp::LNot, exp) => Const::Int(!exp.is_true() as i32),
+    }
+}
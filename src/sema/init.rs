@@ -0,0 +1,106 @@
+//! Reshaping initializers into the shape of arrays.
+
+use super::tree::{Const, Exp, Init, Ty};
+use super::ErrorKind;
+use crate::ast::Span;
+use std::convert::TryFrom;
+use std::iter::repeat_with;
+
+/// Reshapes the initializer by using the given type.
+/// Returns the reshaped initializer.
+pub fn reshape(init: Init, mut ty: &Ty) -> Result<Init, ErrorKind> {
+    // get length list
+    // array `int a[2][3][4]` yields [(4, 4), (3, 12), (2, 24)]
+    let mut lens = Vec::new();
+    while let Ty::Array(base, len) = ty {
+        lens.push(*len);
+        ty = base;
+    }
+    let mut last_len = 1;
+    let lens: Vec<_> = lens
+        .into_iter()
+        .rev()
+        .map(|l| {
+            last_len *= l;
+            (l, last_len)
+        })
+        .collect();
+    let zero = Const::Int(0).cast(ty.base_type().unwrap());
+    // perform reshape
+    match init {
+        v @ Init::Exp(_) if lens.is_empty() => Ok(v),
+        Init::List(l) if !lens.is_empty() => reshape_impl(l, &lens, zero),
+        _ => Err(ErrorKind::InvalidInit),
+    }
+}
+
+fn reshape_impl(inits: Vec<Init>, lens: &[(usize, usize)], zero: Const) -> Result<Init, ErrorKind> {
+    let mut reshaped: Vec<Vec<Init>> = repeat_with(Vec::new).take(lens.len() + 1).collect();
+    let mut len = 0;
+    // handle initializer elements
+    for init in inits {
+        // too many elements
+        if len >= lens.last().unwrap().1 {
+            return Err(ErrorKind::InvalidInit);
+        }
+        match init {
+            Init::List(list) => {
+                // get the next-level length list
+                let next_lens = match reshaped.iter().position(|v| !v.is_empty()) {
+                    // not aligned
+                    Some(0) => return Err(ErrorKind::InvalidInit),
+                    Some(i) => &lens[..i],
+                    // list of the last dimension, which has no next level
+                    None if lens.len() == 1 => return Err(ErrorKind::InvalidInit),
+                    None => &lens[..lens.len() - 1],
+                };
+                // reshape, and add to reshaped initializer list
+                reshaped[next_lens.len()].push(reshape_impl(list, next_lens, zero)?);
+                carry(&mut reshaped, lens);
+                len += next_lens.last().unwrap().1;
+            }
+            _ => {
+                // just push
+                reshaped[0].push(init);
+                carry(&mut reshaped, lens);
+                len += 1;
+            }
+        }
+    }
+    // fill zeros
+    while len < lens.last().unwrap().1 {
+        reshaped[0].push(Init::Exp(Exp::constant(zero, Span::default())));
+        carry(&mut reshaped, lens);
+        len += 1;
+    }
+    Ok(reshaped.pop().unwrap().pop().unwrap())
+}
+
+fn carry(reshaped: &mut [Vec<Init>], lens: &[(usize, usize)]) {
+    // perform carry
+    for (i, &(len, _)) in lens.iter().enumerate() {
+        if reshaped[i].len() == len {
+            let init = Init::List(reshaped[i].drain(..).collect());
+            reshaped[i + 1].push(init);
+        }
+    }
+}
+
+/// Returns the constant element of the reshaped initializer at the given
+/// indices, `None` if the indices are out of bounds or do not select
+/// a constant element.
+pub fn element<I>(mut init: &Init, indices: I) -> Option<Const>
+where
+    I: IntoIterator<Item = i32>,
+{
+    for index in indices {
+        init = match init {
+            Init::List(list) => list.get(usize::try_from(index).ok()?)?,
+            Init::Exp(_) => return None,
+        };
+    }
+    match init {
+        Init::Exp(exp) => exp.as_const(),
+        Init::List(_) => None,
+    }
+}
@@ -0,0 +1,262 @@
+//! Semantic analysis, checks the AST and converts it into a typed and
+//! resolved tree ([`tree`]) for the IR generator.
+
+mod analyze;
+mod flow;
+mod fold;
+mod init;
+mod scopes;
+pub mod tree;
+
+use self::{analyze::Analyze, scopes::Scopes};
+use crate::ast::{CompUnit, Span};
+use std::fmt;
+
+/// Analyzes the given compile unit (ASTs), returns the typed tree
+/// and the warnings of all lints, in the order of their locations.
+///
+/// Analysis continues after errors, all errors are returned
+/// in the order of their locations.
+pub fn analyze(
+    comp_unit: &CompUnit,
+) -> std::result::Result<(tree::CompUnit, Vec<Warning>), Vec<Error>> {
+    let mut scopes = Scopes::new();
+    let items = match comp_unit.analyze(&mut scopes) {
+        Ok(items) => items,
+        Err(err) => {
+            scopes.report(err.kind, err.span);
+            Vec::new()
+        }
+    };
+    let (symbols, diags) = scopes.finish();
+    let Diagnostics {
+        mut errors,
+        mut warnings,
+    } = diags;
+    warnings.sort_by_key(|warning| warning.span.start);
+    if errors.is_empty() {
+        Ok((tree::CompUnit { items, symbols }, warnings))
+    } else {
+        errors.sort_by_key(|err| err.span.start);
+        Err(errors)
+    }
+}
+
+/// Result type of semantic analysis.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Semantic error, with the source span it refers to.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+/// Kind of the semantic error.
+#[derive(Debug)]
+pub enum ErrorKind {
+    DuplicatedDef(String),
+    SymbolNotFound(String),
+    FailedToEval,
+    InvalidArrayLen,
+    InvalidInit,
+    ArrayAssign(String),
+    AssignToConst(String),
+    NotInLoop,
+    RetValInVoidFunc,
+    NoRetValInNonVoidFunc,
+    DerefInt(String),
+    UseVoidValue,
+    ArgMismatch(String),
+    NonIntCalc,
+    /// Signature mismatch, with the span of the earlier declaration.
+    SignatureMismatch(String, Span),
+    UndefinedFunc(String),
+}
+
+impl ErrorKind {
+    /// Returns the span and the message of a note attached to the error.
+    pub fn note(&self) -> Option<(Span, &'static str)> {
+        match self {
+            Self::SignatureMismatch(_, span) => Some((*span, "previously declared here")),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DuplicatedDef(id) => write!(f, "duplicated definition of symbol `{}`", id),
+            Self::SymbolNotFound(id) => write!(f, "symbol `{}` not found", id),
+            Self::FailedToEval => write!(f, "failed to evaluate constant"),
+            Self::InvalidArrayLen => write!(f, "invalid array length"),
+            Self::InvalidInit => write!(f, "invalid initializer"),
+            Self::ArrayAssign(id) => write!(f, "assigning to array `{}`", id),
+            Self::AssignToConst(id) => write!(f, "assigning to constant `{}`", id),
+            Self::NotInLoop => write!(f, "using break/continue outside of loop"),
+            Self::RetValInVoidFunc => write!(f, "returning value in void fucntion"),
+            Self::NoRetValInNonVoidFunc => write!(f, "returning no value in non-void function"),
+            Self::DerefInt(id) => write!(f, "dereferencing integer `{}`", id),
+            Self::UseVoidValue => write!(f, "using a void value"),
+            Self::ArgMismatch(id) => write!(f, "argument mismatch in call to `{}`", id),
+            Self::NonIntCalc => write!(f, "non-integer calculation"),
+            Self::SignatureMismatch(id, _) => {
+                write!(f, "signature of function `{}` does not match its declaration", id)
+            }
+            Self::UndefinedFunc(id) => write!(f, "function `{}` is called but never defined", id),
+        }
+    }
+}
+
+/// Errors and warnings reported by semantic analysis.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub errors: Vec<Error>,
+    pub warnings: Vec<Warning>,
+}
+
+/// Warning reported by semantic analysis, with the source span it refers to.
+#[derive(Debug, PartialEq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub span: Span,
+}
+
+/// Kind of the warning.
+#[derive(Debug, PartialEq)]
+pub enum WarningKind {
+    /// Integer overflow in constant evaluation, with the wrapped result.
+    Overflow(i32),
+    /// Integer literal greater than `i32::MAX`, with the wrapped value.
+    LiteralOverflow(i32),
+    /// Division by zero in constant evaluation, with the result defined by RISC-V.
+    DivByZero(i32),
+    UnusedVar(String),
+    UnusedConst(String),
+    UnusedParam(String),
+    UnusedFunc(String),
+    /// Statement after `return`, `break`, `continue` or an infinite loop.
+    Unreachable,
+    /// Non-void function which may reach its end without returning.
+    MissingReturn(String),
+    /// Local declaration with the same name as an outer declaration.
+    Shadow(String),
+    /// Local variable which may be read before being assigned.
+    Uninit(String),
+}
+
+impl WarningKind {
+    /// Returns the lint which the warning belongs to.
+    pub fn lint(&self) -> Lint {
+        match self {
+            Self::Overflow(_) | Self::LiteralOverflow(_) | Self::DivByZero(_) => Lint::Overflow,
+            Self::UnusedVar(_) | Self::UnusedConst(_) | Self::UnusedParam(_) => Lint::Unused,
+            Self::UnusedFunc(_) => Lint::Unused,
+            Self::Unreachable => Lint::Unreachable,
+            Self::MissingReturn(_) => Lint::Return,
+            Self::Shadow(_) => Lint::Shadow,
+            Self::Uninit(_) => Lint::Uninit,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} [-W{}]", self.kind, self.kind.lint().name())
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Overflow(num) => {
+                write!(f, "integer overflow in constant expression, wraps to `{}`", num)
+            }
+            Self::LiteralOverflow(num) => {
+                write!(f, "integer literal is too large for `int`, wraps to `{}`", num)
+            }
+            Self::DivByZero(num) => {
+                write!(f, "division by zero in constant expression, evaluates to `{}`", num)
+            }
+            Self::UnusedVar(id) => write!(f, "unused variable `{}`", id),
+            Self::UnusedConst(id) => write!(f, "unused constant `{}`", id),
+            Self::UnusedParam(id) => write!(f, "unused parameter `{}`", id),
+            Self::UnusedFunc(id) => write!(f, "function `{}` is never called", id),
+            Self::Unreachable => write!(f, "unreachable statement"),
+            Self::MissingReturn(id) => {
+                write!(f, "non-void function `{}` may reach its end without returning", id)
+            }
+            Self::Shadow(id) => write!(f, "declaration of `{}` shadows an outer declaration", id),
+            Self::Uninit(id) => write!(f, "variable `{}` may be used uninitialized", id),
+        }
+    }
+}
+
+/// Group of warnings which can be enabled or disabled together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// Overflows and divisions by zero in constant evaluation.
+    Overflow,
+    /// Unused local variables, constants, parameters and functions.
+    Unused,
+    /// Unreachable statements.
+    Unreachable,
+    /// Non-void functions without return on some path.
+    Return,
+    /// Local declarations shadowing outer declarations.
+    Shadow,
+    /// Uses of possibly uninitialized local variables.
+    Uninit,
+}
+
+impl Lint {
+    /// All lints.
+    pub const ALL: &'static [Lint] = &[
+        Self::Overflow,
+        Self::Unused,
+        Self::Unreachable,
+        Self::Return,
+        Self::Shadow,
+        Self::Uninit,
+    ];
+
+    /// Returns the lint of the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|l| l.name() == name)
+    }
+
+    /// Returns the name of the lint.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Overflow => "overflow",
+            Self::Unused => "unused",
+            Self::Unreachable => "unreachable",
+            Self::Return => "return",
+            Self::Shadow => "shadow",
+            Self::Uninit => "uninit",
+        }
+    }
+
+    /// Returns `true` if the lint is enabled without `-W` flags.
+    pub fn is_default(self) -> bool {
+        !matches!(self, Self::Shadow)
+    }
+}
+
+/// Helper trait for attaching a source span to an `ErrorKind`.
+pub(crate) trait AtSpan<T> {
+    fn at(self, span: Span) -> Result<T>;
+}
+
+impl<T> AtSpan<T> for std::result::Result<T, ErrorKind> {
+    fn at(self, span: Span) -> Result<T> {
+        self.map_err(|kind| Error { kind, span })
+    }
+}
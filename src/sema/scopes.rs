@@ -0,0 +1,222 @@
+use super::flow::Flow;
+use super::tree::{Signature, Symbol, SymbolId, SymbolKind, Ty};
+use super::{Diagnostics, Error, ErrorKind, Warning, WarningKind};
+use crate::ast::{BType, FuncType, Span};
+use std::collections::{HashMap, HashSet};
+
+/// Scopes of symbols, and the context of the analysis.
+pub struct Scopes<'ast> {
+    vals: Vec<HashMap<&'ast str, SymbolId>>,
+    funcs: HashMap<&'ast str, SymbolId>,
+    symbols: Vec<Symbol>,
+    /// Symbols which have been read or called.
+    used: HashSet<SymbolId>,
+    /// Functions which have been declared but not defined.
+    undefined: HashSet<SymbolId>,
+    /// Number of symbols visible in global scope, global symbols
+    /// defined later in the source are hidden from function bodies.
+    pub visible: usize,
+    /// Return type of the current function, `None` if in global scope.
+    pub cur_ret: Option<FuncType>,
+    /// Control flow state at the current statement.
+    pub flow: Flow,
+    /// Merged control flow states at `break`s of the loops
+    /// around the current statement, innermost last.
+    pub loops: Vec<Flow>,
+    /// Base type of the current declaration.
+    pub decl_ty: BType,
+    diags: Diagnostics,
+}
+
+impl<'ast> Scopes<'ast> {
+    /// Creates a new `Scopes`.
+    pub fn new() -> Self {
+        Self {
+            vals: vec![HashMap::new()],
+            funcs: HashMap::new(),
+            symbols: Vec::new(),
+            used: HashSet::new(),
+            undefined: HashSet::new(),
+            visible: usize::MAX,
+            cur_ret: None,
+            flow: Flow::default(),
+            loops: Vec::new(),
+            decl_ty: BType::Int,
+            diags: Diagnostics::default(),
+        }
+    }
+
+    /// Finishes the analysis, returns all symbols and diagnostics.
+    pub fn finish(self) -> (Vec<Symbol>, Diagnostics) {
+        (self.symbols, self.diags)
+    }
+
+    /// Reports an error at the given span, analysis continues after it.
+    pub fn report(&mut self, kind: ErrorKind, span: Span) {
+        trace!(Sema, "error at {}..{}: {}", span.start, span.end, kind);
+        self.diags.errors.push(Error { kind, span });
+    }
+
+    /// Reports a warning at the given span.
+    pub fn warn(&mut self, kind: WarningKind, span: Span) {
+        self.diags.warnings.push(Warning { kind, span });
+    }
+
+    /// Returns `true` if is currently in global scope.
+    pub fn is_global(&self) -> bool {
+        self.cur_ret.is_none()
+    }
+
+    /// Creates a new symbol which can not be referenced by name,
+    /// for definitions with errors.
+    pub fn new_symbol(&mut self, name: &str, kind: SymbolKind, span: Span) -> SymbolId {
+        let id = SymbolId(self.symbols.len());
+        let name = name.into();
+        self.symbols.push(Symbol { name, kind, span });
+        id
+    }
+
+    /// Returns the symbol of the given id.
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    /// Returns the number of symbols created so far.
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Marks the symbol as used.
+    pub fn use_symbol(&mut self, id: SymbolId) {
+        self.used.insert(id);
+    }
+
+    /// Returns `true` if the symbol has been used.
+    pub fn is_used(&self, id: SymbolId) -> bool {
+        self.used.contains(&id)
+    }
+
+    /// Inserts a new value to the current scope.
+    pub fn new_value(
+        &mut self,
+        id: &'ast str,
+        kind: SymbolKind,
+        span: Span,
+    ) -> Result<SymbolId, ErrorKind> {
+        let is_global = self.is_global();
+        let cur = self.vals.last().unwrap();
+        if cur.contains_key(id) || (is_global && self.funcs.contains_key(id)) {
+            return Err(ErrorKind::DuplicatedDef(id.into()));
+        }
+        trace!(Sema, "scope {}: define `{}` as {:?}", self.vals.len() - 1, id, kind);
+        let depth = self.vals.len() - 1;
+        if !is_global && self.find(depth, id).is_some() {
+            self.warn(WarningKind::Shadow(id.into()), span);
+        }
+        let symbol = self.new_symbol(id, kind, span);
+        self.vals.last_mut().unwrap().insert(id, symbol);
+        Ok(symbol)
+    }
+
+    /// Returns the value by the given identifier.
+    pub fn value(&self, id: &str) -> Result<SymbolId, ErrorKind> {
+        self.find(self.vals.len(), id)
+            .ok_or_else(|| ErrorKind::SymbolNotFound(id.into()))
+    }
+
+    /// Finds the visible value in the outermost `depth` scopes,
+    /// innermost first.
+    fn find(&self, depth: usize, id: &str) -> Option<SymbolId> {
+        self.vals[..depth]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(d, vals)| vals.get(id).copied().filter(|s| d > 0 || s.0 < self.visible))
+    }
+
+    /// Declares a function in the global scope, or defines it if `define`
+    /// is `true`. A function can be declared more than once with the same
+    /// signature, but can only be defined once.
+    pub fn declare_func(
+        &mut self,
+        id: &'ast str,
+        sig: Signature,
+        span: Span,
+        define: bool,
+    ) -> Result<SymbolId, ErrorKind> {
+        if self.vals.first().unwrap().contains_key(id) {
+            return Err(ErrorKind::DuplicatedDef(id.into()));
+        }
+        if let Some(&symbol) = self.funcs.get(id) {
+            if !matches!(&self.symbol(symbol).kind, SymbolKind::Func(prev) if *prev == sig) {
+                // the declaration is defined by the mismatched definition,
+                // do not report it as undefined again
+                if define {
+                    self.undefined.remove(&symbol);
+                }
+                let prev = self.symbol(symbol).span;
+                return Err(ErrorKind::SignatureMismatch(id.into(), prev));
+            }
+            if define {
+                if !self.undefined.remove(&symbol) {
+                    return Err(ErrorKind::DuplicatedDef(id.into()));
+                }
+                // point at the definition of the declared function
+                self.symbols[symbol.0].span = span;
+            }
+            return Ok(symbol);
+        }
+        trace!(Sema, "declare function `{}` as {:?}", id, sig);
+        let symbol = self.new_symbol(id, SymbolKind::Func(sig), span);
+        self.funcs.insert(id, symbol);
+        if !define {
+            self.undefined.insert(symbol);
+        }
+        Ok(symbol)
+    }
+
+    /// Returns all functions which have been declared but not defined,
+    /// in the order of declarations.
+    pub fn undefined_funcs(&self) -> Vec<SymbolId> {
+        let mut funcs: Vec<_> = self.undefined.iter().copied().collect();
+        funcs.sort_by_key(|id| id.0);
+        funcs
+    }
+
+    /// Returns the function and its signature by the given identifier.
+    pub fn func(&self, id: &str) -> Result<(SymbolId, &Signature), ErrorKind> {
+        let symbol = *self
+            .funcs
+            .get(id)
+            .ok_or_else(|| ErrorKind::SymbolNotFound(id.into()))?;
+        match &self.symbol(symbol).kind {
+            SymbolKind::Func(sig) => Ok((symbol, sig)),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Enters a new scope.
+    pub fn enter(&mut self) {
+        self.vals.push(HashMap::new());
+    }
+
+    /// Exits from the current scope, warns unused symbols in it.
+    pub fn exit(&mut self) {
+        let vals = self.vals.pop().unwrap();
+        // the outermost scope of a function only contains parameters
+        let is_params = self.vals.len() == 1;
+        for (name, id) in vals {
+            if self.is_used(id) {
+                continue;
+            }
+            let name = name.into();
+            let kind = match &self.symbol(id).kind {
+                SymbolKind::Var(Ty::Error) => continue,
+                SymbolKind::Var(_) if is_params => WarningKind::UnusedParam(name),
+                SymbolKind::Var(_) => WarningKind::UnusedVar(name),
+                _ => WarningKind::UnusedConst(name),
+            };
+            self.warn(kind, self.symbol(id).span);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Typed and resolved AST, the output of semantic analysis.
+//!
+//! Identifiers are resolved into symbols, every expression carries its
+//! type, implicit conversions are explicit [`ExpKind::Cast`] nodes,
+//! and constant expressions are folded into literals.
+
+use crate::ast::{BType, FuncType, Span};
+
+pub use crate::ast::UnaryOp;
+
+/// A compile unit, with all symbols defined in it.
+#[derive(Debug)]
+pub struct CompUnit {
+    pub items: Vec<GlobalItem>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl CompUnit {
+    /// Returns the symbol of the given id.
+    pub fn symbol(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+}
+
+/// Index of a symbol in [`CompUnit::symbols`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolId(pub usize);
+
+/// A variable, constant or function.
+#[derive(Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Span of the definition, empty for library functions.
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum SymbolKind {
+    /// Variable or parameter, with its type.
+    Var(Ty),
+    /// Constant number, which is folded into its uses.
+    Const(Const),
+    /// Constant array, with its type and reshaped initializer.
+    ConstArray(Ty, Init),
+    /// Function, with its signature.
+    Func(Signature),
+}
+
+/// Signature of a function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub params: Vec<Ty>,
+    pub ret: FuncType,
+}
+
+/// Type of variables, parameters and expressions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    Void,
+    /// Array, with the type and the number of elements.
+    Array(Box<Ty>, usize),
+    /// Pointer to elements, the type of array parameters.
+    Pointer(Box<Ty>),
+    /// Type of erroneous expressions and symbols, whose errors have been
+    /// reported. Checks always pass on it to avoid cascading errors.
+    Error,
+}
+
+impl Ty {
+    /// Returns the base type if the type is an integer or a float.
+    pub fn base_type(&self) -> Option<BType> {
+        match self {
+            Self::Int => Some(BType::Int),
+            Self::Float => Some(BType::Float),
+            _ => None,
+        }
+    }
+
+    /// Returns the type of elements if the type is an array or a pointer.
+    pub fn elem(&self) -> Option<&Ty> {
+        match self {
+            Self::Array(elem, _) | Self::Pointer(elem) => Some(elem),
+            _ => None,
+        }
+    }
+
+    /// Returns the type after converting arrays into pointers
+    /// to their first elements.
+    pub fn decay(&self) -> Ty {
+        match self {
+            Self::Array(elem, _) => Self::Pointer(elem.clone()),
+            ty => ty.clone(),
+        }
+    }
+}
+
+impl From<BType> for Ty {
+    fn from(ty: BType) -> Self {
+        match ty {
+            BType::Int => Self::Int,
+            BType::Float => Self::Float,
+        }
+    }
+}
+
+impl From<FuncType> for Ty {
+    fn from(ty: FuncType) -> Self {
+        match ty {
+            FuncType::Void => Self::Void,
+            FuncType::Int => Self::Int,
+            FuncType::Float => Self::Float,
+        }
+    }
+}
+
+/// A constant integer or float.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Const {
+    Int(i32),
+    Float(f32),
+}
+
+impl Const {
+    /// Returns the type of the constant.
+    pub fn ty(self) -> BType {
+        match self {
+            Self::Int(_) => BType::Int,
+            Self::Float(_) => BType::Float,
+        }
+    }
+
+    /// Converts the constant to the given type.
+    pub fn cast(self, ty: BType) -> Self {
+        match (self, ty) {
+            (Self::Int(i), BType::Float) => Self::Float(i as f32),
+            (Self::Float(f), BType::Int) => Self::Int(f as i32),
+            _ => self,
+        }
+    }
+
+    /// Returns `true` if the constant is not zero.
+    pub fn is_true(self) -> bool {
+        match self {
+            Self::Int(i) => i != 0,
+            Self::Float(f) => f != 0.0,
+        }
+    }
+
+    /// Returns the representation of the constant in Koopa IR.
+    pub fn to_bits(self) -> i32 {
+        match self {
+            Self::Int(i) => i,
+            Self::Float(f) => f.to_bits() as i32,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GlobalItem {
+    Decl(VarDef),
+    FuncDef(FuncDef),
+}
+
+#[derive(Debug)]
+pub struct FuncDef {
+    pub id: SymbolId,
+    pub params: Vec<SymbolId>,
+    pub block: Block,
+}
+
+#[derive(Debug)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    /// Definition of a variable or a constant array.
+    Decl(VarDef),
+    /// Assignment, the left-hand side is a left value.
+    Assign(Exp, Exp),
+    Exp(Option<Exp>),
+    Block(Block),
+    If(Exp, Box<Stmt>, Option<Box<Stmt>>),
+    While(Exp, Box<Stmt>),
+    Break,
+    Continue,
+    /// Return, the value has been converted to the return type.
+    Return(Option<Exp>),
+}
+
+/// Definition of a variable or a constant array.
+#[derive(Debug)]
+pub struct VarDef {
+    pub id: SymbolId,
+    pub init: Option<Init>,
+}
+
+/// Initializer reshaped to the type of the variable, missing elements
+/// are filled with zeros. Elements are constants in global scope.
+#[derive(Debug, Clone)]
+pub enum Init {
+    Exp(Exp),
+    List(Vec<Init>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Exp {
+    pub kind: ExpKind,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExpKind {
+    Const(Const),
+    /// Left value, with integer indices. Evaluates to an array if
+    /// it is not fully indexed.
+    LVal(SymbolId, Vec<Exp>),
+    /// Function call, arguments have been converted to parameter types.
+    Call(SymbolId, Vec<Exp>),
+    Unary(UnaryOp, Box<Exp>),
+    /// Binary operation, operands have been converted to the same type.
+    Binary(BinaryOp, Box<Exp>, Box<Exp>),
+    LAnd(Box<Exp>, Box<Exp>),
+    LOr(Box<Exp>, Box<Exp>),
+    /// Conversion between integer and float, to the type of the expression.
+    Cast(Box<Exp>),
+    /// Erroneous expression, whose errors have been reported.
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    NotEq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl BinaryOp {
+    /// Returns `true` if the operator is a comparison.
+    pub fn is_compare(self) -> bool {
+        !matches!(
+            self,
+            Self::Add | Self::Sub | Self::Mul | Self::Div | Self::Mod
+        )
+    }
+}
+
+impl Exp {
+    /// Creates a constant expression.
+    pub fn constant(num: Const, span: Span) -> Self {
+        Self {
+            kind: ExpKind::Const(num),
+            ty: num.ty().into(),
+            span,
+        }
+    }
+
+    /// Creates an erroneous expression.
+    pub fn error(span: Span) -> Self {
+        Self {
+            kind: ExpKind::Error,
+            ty: Ty::Error,
+            span,
+        }
+    }
+
+    /// Returns the constant if the expression is a constant.
+    pub fn as_const(&self) -> Option<Const> {
+        match self.kind {
+            ExpKind::Const(num) => Some(num),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use super::{Error, ErrorKind, Result};
+use std::collections::HashMap;
+use sylib::LibFunc;
+
+/// Address of the first instruction.
+pub const TEXT_BASE: u32 = 0x1000;
+/// Address of the data section.
+pub const DATA_BASE: u32 = 0x10000;
+/// Size of the memory, the stack grows down from the end of it.
+pub const MEM_SIZE: u32 = 0x4000000;
+
+/// Index of an integer register.
+pub type Reg = usize;
+/// Index of a float register.
+pub type FReg = usize;
+
+/// Assembled program.
+pub struct Program {
+    /// Instructions, with the lines they come from.
+    pub insts: Vec<(Inst, usize)>,
+    /// Initial content of the data section.
+    pub data: Vec<u8>,
+    /// Index of the first instruction of `main`.
+    pub entry: usize,
+}
+
+/// Returns the address of the instruction at the given index.
+pub fn inst_addr(index: usize) -> u32 {
+    TEXT_BASE + index as u32 * 4
+}
+
+/// Instructions, pseudo instructions are expanded to them while assembling.
+pub enum Inst {
+    Op(Op, Reg, Reg, Reg),
+    OpImm(Op, Reg, Reg, i32),
+    Li(Reg, i32),
+    Load(Width, Reg, Reg, i32),
+    Store(Width, Reg, Reg, i32),
+    /// Branch to the instruction at the given index.
+    Branch(Cond, Reg, Reg, usize),
+    /// Jump to the instruction at the given index.
+    Jal(Reg, usize),
+    Jalr(Reg, Reg, i32),
+    /// Call to a function in SysY runtime library.
+    Lib(LibFunc),
+    FLoad(FReg, Reg, i32),
+    FStore(FReg, Reg, i32),
+    FOp(FOp, FReg, FReg, FReg),
+    FCmp(FCmp, Reg, FReg, FReg),
+    /// `fmv.w.x`
+    FMvWX(FReg, Reg),
+    /// `fmv.x.w`
+    FMvXW(Reg, FReg),
+    /// `fcvt.s.w`
+    FCvtSW(FReg, Reg),
+    /// `fcvt.w.s`, rounds towards zero if the flag is set,
+    /// or to nearest otherwise.
+    FCvtWS(Reg, FReg, bool),
+}
+
+#[derive(Clone, Copy)]
+pub enum Op {
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+}
+
+#[derive(Clone, Copy)]
+pub enum Width {
+    Byte,
+    Half,
+    Word,
+    ByteU,
+    HalfU,
+}
+
+#[derive(Clone, Copy)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+#[derive(Clone, Copy)]
+pub enum FOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Clone, Copy)]
+pub enum FCmp {
+    Eq,
+    Lt,
+    Le,
+}
+
+/// Symbols defined by labels.
+#[derive(Clone, Copy)]
+enum Symbol {
+    /// Index of an instruction.
+    Text(usize),
+    /// Address in the data section.
+    Data(u32),
+}
+
+/// Assembles the given assembly.
+pub fn assemble(asm: &str) -> Result<Program> {
+    // collect labels, data and instructions
+    let mut symbols = HashMap::new();
+    let mut data = Vec::new();
+    let mut lines = Vec::new();
+    let mut in_text = true;
+    for (i, line) in asm.lines().enumerate() {
+        let mut line = line.split('#').next().unwrap().trim();
+        while let Some(pos) = line.find(':').filter(|&pos| is_symbol(&line[..pos])) {
+            let label = &line[..pos];
+            let symbol = if in_text {
+                Symbol::Text(lines.len())
+            } else {
+                Symbol::Data(DATA_BASE + data.len() as u32)
+            };
+            if symbols.insert(label, symbol).is_some() {
+                return Err(Error::at(ErrorKind::DuplicatedLabel(label.into()), i));
+            }
+            line = line[pos + 1..].trim_start();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let (mnemonic, oprs) = match line.find(char::is_whitespace) {
+            Some(pos) => (
+                &line[..pos],
+                line[pos..].split(',').map(str::trim).collect(),
+            ),
+            None => (line, Vec::new()),
+        };
+        if mnemonic.starts_with('.') {
+            directive(mnemonic, &oprs, &mut in_text, &mut data).map_err(|e| Error::at(e, i))?;
+        } else if in_text {
+            lines.push((mnemonic, oprs, i));
+        } else {
+            return Err(Error::at(ErrorKind::InstInData, i));
+        }
+    }
+    if data.len() as u32 > (MEM_SIZE - DATA_BASE) / 2 {
+        return Err(ErrorKind::DataTooLarge.into());
+    }
+    // assemble instructions
+    let insts = lines
+        .into_iter()
+        .map(|(mnemonic, oprs, i)| {
+            let inst = instruction(mnemonic, &oprs, &symbols).map_err(|e| Error::at(e, i))?;
+            Ok((inst, i))
+        })
+        .collect::<Result<_>>()?;
+    match symbols.get("main") {
+        Some(&Symbol::Text(entry)) => Ok(Program { insts, data, entry }),
+        _ => Err(ErrorKind::NoMain.into()),
+    }
+}
+
+/// Checks if the given string is a valid symbol.
+fn is_symbol(s: &str) -> bool {
+    !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '$')
+}
+
+/// Handles a directive.
+fn directive(
+    name: &str,
+    oprs: &[&str],
+    in_text: &mut bool,
+    data: &mut Vec<u8>,
+) -> std::result::Result<(), ErrorKind> {
+    let size = match name {
+        ".text" => {
+            *in_text = true;
+            return Ok(());
+        }
+        ".data" | ".bss" | ".rodata" | ".sdata" | ".sbss" => {
+            *in_text = false;
+            return Ok(());
+        }
+        ".section" => {
+            let section = oprs.first().copied().unwrap_or_default();
+            *in_text = section.starts_with(".text");
+            return Ok(());
+        }
+        ".globl" | ".global" | ".local" | ".type" | ".size" | ".file" | ".ident" | ".option"
+        | ".attribute" => return Ok(()),
+        ".align" | ".p2align" => {
+            if !*in_text {
+                let align = 1 << imm_in(single(oprs)?, 0, 12)?;
+                let len = data.len().div_ceil(align) * align;
+                data.resize(len, 0);
+            }
+            return Ok(());
+        }
+        ".zero" | ".space" => {
+            let len = imm_in(single(oprs)?, 0, (MEM_SIZE - DATA_BASE) as i64)?;
+            return data_only(*in_text).map(|_| data.resize(data.len() + len as usize, 0));
+        }
+        ".byte" => 1,
+        ".half" | ".short" => 2,
+        ".word" | ".long" => 4,
+        _ => return Err(ErrorKind::UnknownDirective(name.into())),
+    };
+    data_only(*in_text)?;
+    for opr in oprs {
+        let value = imm_in(opr, i32::MIN as i64, u32::MAX as i64)? as u32;
+        data.extend_from_slice(&value.to_le_bytes()[..size]);
+    }
+    Ok(())
+}
+
+/// Returns an error if data is emitted to the text section.
+fn data_only(in_text: bool) -> std::result::Result<(), ErrorKind> {
+    if in_text {
+        Err(ErrorKind::DataInText)
+    } else {
+        Ok(())
+    }
+}
+
+/// Assembles an instruction.
+fn instruction(
+    mnemonic: &str,
+    oprs: &[&str],
+    symbols: &HashMap<&str, Symbol>,
+) -> std::result::Result<Inst, ErrorKind> {
+    let target = |opr: &str| match symbols.get(opr) {
+        Some(&Symbol::Text(index)) => Ok(index),
+        _ => Err(ErrorKind::UndefinedSymbol(opr.into())),
+    };
+    let imm12 = |opr: &str| imm_in(opr, -2048, 2047).map(|imm| imm as i32);
+    let inst = match (mnemonic, oprs) {
+        // pseudo instructions
+        ("nop", []) => Inst::OpImm(Op::Add, 0, 0, 0),
+        ("li", [rd, imm]) => Inst::Li(
+            reg(rd)?,
+            imm_in(imm, i32::MIN as i64, u32::MAX as i64)? as i32,
+        ),
+        ("la", [rd, symbol]) => {
+            let addr = match symbols.get(symbol) {
+                Some(&Symbol::Text(index)) => inst_addr(index),
+                Some(&Symbol::Data(addr)) => addr,
+                None => return Err(ErrorKind::UndefinedSymbol(symbol.to_string())),
+            };
+            Inst::Li(reg(rd)?, addr as i32)
+        }
+        ("mv", [rd, rs]) => Inst::OpImm(Op::Add, reg(rd)?, reg(rs)?, 0),
+        ("not", [rd, rs]) => Inst::OpImm(Op::Xor, reg(rd)?, reg(rs)?, -1),
+        ("neg", [rd, rs]) => Inst::Op(Op::Sub, reg(rd)?, 0, reg(rs)?),
+        ("seqz", [rd, rs]) => Inst::OpImm(Op::Sltu, reg(rd)?, reg(rs)?, 1),
+        ("snez", [rd, rs]) => Inst::Op(Op::Sltu, reg(rd)?, 0, reg(rs)?),
+        ("sltz", [rd, rs]) => Inst::Op(Op::Slt, reg(rd)?, reg(rs)?, 0),
+        ("sgtz", [rd, rs]) => Inst::Op(Op::Slt, reg(rd)?, 0, reg(rs)?),
+        ("sgt", [rd, rs1, rs2]) => Inst::Op(Op::Slt, reg(rd)?, reg(rs2)?, reg(rs1)?),
+        ("sgtu", [rd, rs1, rs2]) => Inst::Op(Op::Sltu, reg(rd)?, reg(rs2)?, reg(rs1)?),
+        ("beqz", [rs, label]) => Inst::Branch(Cond::Eq, reg(rs)?, 0, target(label)?),
+        ("bnez", [rs, label]) => Inst::Branch(Cond::Ne, reg(rs)?, 0, target(label)?),
+        ("blez", [rs, label]) => Inst::Branch(Cond::Ge, 0, reg(rs)?, target(label)?),
+        ("bgez", [rs, label]) => Inst::Branch(Cond::Ge, reg(rs)?, 0, target(label)?),
+        ("bltz", [rs, label]) => Inst::Branch(Cond::Lt, reg(rs)?, 0, target(label)?),
+        ("bgtz", [rs, label]) => Inst::Branch(Cond::Lt, 0, reg(rs)?, target(label)?),
+        ("bgt", [rs1, rs2, label]) => Inst::Branch(Cond::Lt, reg(rs2)?, reg(rs1)?, target(label)?),
+        ("ble", [rs1, rs2, label]) => Inst::Branch(Cond::Ge, reg(rs2)?, reg(rs1)?, target(label)?),
+        ("bgtu", [rs1, rs2, label]) => {
+            Inst::Branch(Cond::Ltu, reg(rs2)?, reg(rs1)?, target(label)?)
+        }
+        ("bleu", [rs1, rs2, label]) => {
+            Inst::Branch(Cond::Geu, reg(rs2)?, reg(rs1)?, target(label)?)
+        }
+        ("j", [label]) => Inst::Jal(0, target(label)?),
+        ("jal", [label]) => Inst::Jal(1, target(label)?),
+        ("jr", [rs]) => Inst::Jalr(0, reg(rs)?, 0),
+        ("jalr", [rs]) => Inst::Jalr(1, reg(rs)?, 0),
+        ("ret", []) => Inst::Jalr(0, 1, 0),
+        ("call", [func]) => match symbols.get(func) {
+            Some(&Symbol::Text(index)) => Inst::Jal(1, index),
+            _ => Inst::Lib(
+                LibFunc::from_name(func)
+                    .ok_or_else(|| ErrorKind::UndefinedSymbol(func.to_string()))?,
+            ),
+        },
+        ("tail", [func]) => Inst::Jal(0, target(func)?),
+        ("fgt.s", [rd, fs1, fs2]) => Inst::FCmp(FCmp::Lt, reg(rd)?, freg(fs2)?, freg(fs1)?),
+        ("fge.s", [rd, fs1, fs2]) => Inst::FCmp(FCmp::Le, reg(rd)?, freg(fs2)?, freg(fs1)?),
+        // RV32I
+        ("lui", [rd, imm]) => Inst::Li(reg(rd)?, (imm_in(imm, 0, 0xfffff)? << 12) as i32),
+        ("jal", [rd, label]) => Inst::Jal(reg(rd)?, target(label)?),
+        ("jalr", [rd, rs, imm]) => Inst::Jalr(reg(rd)?, reg(rs)?, imm12(imm)?),
+        ("jalr", [rd, addr]) => {
+            let (offset, rs) = mem(addr)?;
+            Inst::Jalr(reg(rd)?, reg(rs)?, imm12(offset)?)
+        }
+        ("slli", [rd, rs, shamt]) => {
+            Inst::OpImm(Op::Sll, reg(rd)?, reg(rs)?, imm_in(shamt, 0, 31)? as i32)
+        }
+        ("srli", [rd, rs, shamt]) => {
+            Inst::OpImm(Op::Srl, reg(rd)?, reg(rs)?, imm_in(shamt, 0, 31)? as i32)
+        }
+        ("srai", [rd, rs, shamt]) => {
+            Inst::OpImm(Op::Sra, reg(rd)?, reg(rs)?, imm_in(shamt, 0, 31)? as i32)
+        }
+        (name, [rd, rs, imm]) if imm_op(name).is_some() => {
+            Inst::OpImm(imm_op(name).unwrap(), reg(rd)?, reg(rs)?, imm12(imm)?)
+        }
+        (name, [rd, rs1, rs2]) if reg_op(name).is_some() => {
+            Inst::Op(reg_op(name).unwrap(), reg(rd)?, reg(rs1)?, reg(rs2)?)
+        }
+        (name, [rs1, rs2, label]) if cond(name).is_some() => {
+            Inst::Branch(cond(name).unwrap(), reg(rs1)?, reg(rs2)?, target(label)?)
+        }
+        (name, [rd, addr]) if load_width(name).is_some() => {
+            let (offset, rs) = mem(addr)?;
+            Inst::Load(
+                load_width(name).unwrap(),
+                reg(rd)?,
+                reg(rs)?,
+                imm12(offset)?,
+            )
+        }
+        (name, [rs2, addr]) if store_width(name).is_some() => {
+            let (offset, rs1) = mem(addr)?;
+            Inst::Store(
+                store_width(name).unwrap(),
+                reg(rs2)?,
+                reg(rs1)?,
+                imm12(offset)?,
+            )
+        }
+        // RV32F
+        ("flw", [fd, addr]) => {
+            let (offset, rs) = mem(addr)?;
+            Inst::FLoad(freg(fd)?, reg(rs)?, imm12(offset)?)
+        }
+        ("fsw", [fs, addr]) => {
+            let (offset, rs) = mem(addr)?;
+            Inst::FStore(freg(fs)?, reg(rs)?, imm12(offset)?)
+        }
+        ("fmv.w.x", [fd, rs]) => Inst::FMvWX(freg(fd)?, reg(rs)?),
+        ("fmv.x.w", [rd, fs]) => Inst::FMvXW(reg(rd)?, freg(fs)?),
+        ("fcvt.s.w", [fd, rs]) | ("fcvt.s.w", [fd, rs, "rne"]) | ("fcvt.s.w", [fd, rs, "dyn"]) => {
+            Inst::FCvtSW(freg(fd)?, reg(rs)?)
+        }
+        ("fcvt.w.s", [rd, fs]) | ("fcvt.w.s", [rd, fs, "rne"]) | ("fcvt.w.s", [rd, fs, "dyn"]) => {
+            Inst::FCvtWS(reg(rd)?, freg(fs)?, false)
+        }
+        ("fcvt.w.s", [rd, fs, "rtz"]) => Inst::FCvtWS(reg(rd)?, freg(fs)?, true),
+        (name, [fd, fs1, fs2]) if float_op(name).is_some() => {
+            Inst::FOp(float_op(name).unwrap(), freg(fd)?, freg(fs1)?, freg(fs2)?)
+        }
+        (name, [rd, fs1, fs2]) if float_cmp(name).is_some() => {
+            Inst::FCmp(float_cmp(name).unwrap(), reg(rd)?, freg(fs1)?, freg(fs2)?)
+        }
+        _ if is_known(mnemonic) => return Err(ErrorKind::InvalidOperand(oprs.join(", "))),
+        _ => return Err(ErrorKind::UnknownInst(mnemonic.into())),
+    };
+    Ok(inst)
+}
+
+/// Returns the register-register operation of the given name.
+fn reg_op(name: &str) -> Option<Op> {
+    Some(match name {
+        "add" => Op::Add,
+        "sub" => Op::Sub,
+        "sll" => Op::Sll,
+        "slt" => Op::Slt,
+        "sltu" => Op::Sltu,
+        "xor" => Op::Xor,
+        "srl" => Op::Srl,
+        "sra" => Op::Sra,
+        "or" => Op::Or,
+        "and" => Op::And,
+        "mul" => Op::Mul,
+        "mulh" => Op::Mulh,
+        "mulhsu" => Op::Mulhsu,
+        "mulhu" => Op::Mulhu,
+        "div" => Op::Div,
+        "divu" => Op::Divu,
+        "rem" => Op::Rem,
+        "remu" => Op::Remu,
+        _ => return None,
+    })
+}
+
+/// Returns the register-immediate operation of the given name, except shifts.
+fn imm_op(name: &str) -> Option<Op> {
+    Some(match name {
+        "addi" => Op::Add,
+        "slti" => Op::Slt,
+        "sltiu" => Op::Sltu,
+        "xori" => Op::Xor,
+        "ori" => Op::Or,
+        "andi" => Op::And,
+        _ => return None,
+    })
+}
+
+/// Returns the condition of the given branch instruction.
+fn cond(name: &str) -> Option<Cond> {
+    Some(match name {
+        "beq" => Cond::Eq,
+        "bne" => Cond::Ne,
+        "blt" => Cond::Lt,
+        "bge" => Cond::Ge,
+        "bltu" => Cond::Ltu,
+        "bgeu" => Cond::Geu,
+        _ => return None,
+    })
+}
+
+/// Returns the width of the given load instruction.
+fn load_width(name: &str) -> Option<Width> {
+    Some(match name {
+        "lb" => Width::Byte,
+        "lh" => Width::Half,
+        "lw" => Width::Word,
+        "lbu" => Width::ByteU,
+        "lhu" => Width::HalfU,
+        _ => return None,
+    })
+}
+
+/// Returns the width of the given store instruction.
+fn store_width(name: &str) -> Option<Width> {
+    Some(match name {
+        "sb" => Width::Byte,
+        "sh" => Width::Half,
+        "sw" => Width::Word,
+        _ => return None,
+    })
+}
+
+/// Returns the float operation of the given name.
+fn float_op(name: &str) -> Option<FOp> {
+    Some(match name {
+        "fadd.s" => FOp::Add,
+        "fsub.s" => FOp::Sub,
+        "fmul.s" => FOp::Mul,
+        "fdiv.s" => FOp::Div,
+        _ => return None,
+    })
+}
+
+/// Returns the float comparison of the given name.
+fn float_cmp(name: &str) -> Option<FCmp> {
+    Some(match name {
+        "feq.s" => FCmp::Eq,
+        "flt.s" => FCmp::Lt,
+        "fle.s" => FCmp::Le,
+        _ => return None,
+    })
+}
+
+/// Checks if the given mnemonic is supported, used for reporting
+/// invalid operands of known instructions.
+fn is_known(name: &str) -> bool {
+    const OTHERS: &[&str] = &[
+        "nop", "li", "la", "mv", "not", "neg", "seqz", "snez", "sltz", "sgtz", "sgt", "sgtu",
+        "beqz", "bnez", "blez", "bgez", "bltz", "bgtz", "bgt", "ble", "bgtu", "bleu", "j", "jal",
+        "jr", "jalr", "ret", "call", "tail", "lui", "slli", "srli", "srai", "flw", "fsw",
+        "fmv.w.x", "fmv.x.w", "fcvt.s.w", "fcvt.w.s", "fgt.s", "fge.s",
+    ];
+    OTHERS.contains(&name)
+        || reg_op(name).is_some()
+        || imm_op(name).is_some()
+        || cond(name).is_some()
+        || load_width(name).is_some()
+        || store_width(name).is_some()
+        || float_op(name).is_some()
+        || float_cmp(name).is_some()
+}
+
+/// Returns the only operand.
+fn single<'a>(oprs: &[&'a str]) -> std::result::Result<&'a str, ErrorKind> {
+    match oprs {
+        [opr] => Ok(opr),
+        _ => Err(ErrorKind::InvalidOperand(oprs.join(", "))),
+    }
+}
+
+/// Parses an immediate in the given range (inclusive).
+fn imm_in(opr: &str, min: i64, max: i64) -> std::result::Result<i64, ErrorKind> {
+    let (neg, abs) = match opr.strip_prefix('-') {
+        Some(abs) => (true, abs),
+        None => (false, opr),
+    };
+    let abs = match abs.strip_prefix("0x").or_else(|| abs.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => abs.parse(),
+    }
+    .map_err(|_| ErrorKind::InvalidOperand(opr.into()))?;
+    let imm = if neg { -abs } else { abs };
+    if imm < min || imm > max {
+        return Err(ErrorKind::ImmOutOfRange(imm));
+    }
+    Ok(imm)
+}
+
+/// Parses a memory operand in form `offset(reg)`, returns the offset and the register.
+fn mem(opr: &str) -> std::result::Result<(&str, &str), ErrorKind> {
+    let invalid = || ErrorKind::InvalidOperand(opr.into());
+    let (offset, rest) = opr.split_at(opr.find('(').ok_or_else(invalid)?);
+    let reg = rest[1..].strip_suffix(')').ok_or_else(invalid)?.trim();
+    let offset = offset.trim();
+    Ok((if offset.is_empty() { "0" } else { offset }, reg))
+}
+
+/// ABI names of integer registers.
+const REGS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// ABI names of float registers.
+const FREGS: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+/// Parses an integer register.
+fn reg(opr: &str) -> std::result::Result<Reg, ErrorKind> {
+    if opr == "fp" {
+        return Ok(8);
+    }
+    REGS.iter()
+        .position(|&r| r == opr)
+        .or_else(|| reg_index(opr, "x"))
+        .ok_or_else(|| ErrorKind::InvalidOperand(opr.into()))
+}
+
+/// Parses a float register.
+fn freg(opr: &str) -> std::result::Result<FReg, ErrorKind> {
+    FREGS
+        .iter()
+        .position(|&r| r == opr)
+        .or_else(|| reg_index(opr, "f"))
+        .ok_or_else(|| ErrorKind::InvalidOperand(opr.into()))
+}
+
+/// Parses a register in form `{prefix}{index}`.
+fn reg_index(opr: &str, prefix: &str) -> Option<usize> {
+    let index = opr.strip_prefix(prefix)?;
+    if index.len() > 1 && index.starts_with('0') {
+        return None;
+    }
+    index.parse().ok().filter(|&i| i < 32)
+}
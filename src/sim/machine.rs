@@ -0,0 +1,270 @@
+use super::asm::*;
+use super::{Error, ErrorKind, Result};
+use intrinsic::{float_bits, float_to_int};
+use std::io::{BufRead, Write};
+use sylib::{Runtime, Stats};
+
+/// Return address of `main`, jumping to it exits the program.
+const EXIT_ADDR: u32 = 0;
+
+/// Register index of `ra`.
+const RA: Reg = 1;
+/// Register index of `sp`.
+const SP: Reg = 2;
+/// Register index of `a0`.
+pub const A0: Reg = 10;
+/// Register index of `a1`.
+pub const A1: Reg = 11;
+
+/// Caller-saved integer registers.
+const CALLER_SAVED_REGS: [Reg; 15] = [5, 6, 7, 10, 11, 12, 13, 14, 15, 16, 17, 28, 29, 30, 31];
+
+/// Caller-saved float registers.
+const CALLER_SAVED_FREGS: [FReg; 20] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 10, 11, 12, 13, 14, 15, 16, 17, 28, 29, 30, 31,
+];
+
+/// Value written to caller-saved registers after library calls,
+/// helps exposing values wrongly kept in them across calls.
+const CLOBBERED: u32 = 0xdeadbeef;
+
+/// RV32IMF machine running an assembled program.
+pub struct Machine<'a, R, W> {
+    program: &'a Program,
+    regs: [i32; 32],
+    /// Bit patterns of float registers.
+    fregs: [u32; 32],
+    mem: Vec<u8>,
+    /// Index of the next instruction.
+    pc: usize,
+    pub runtime: Runtime<R, W>,
+    /// Number of executed instructions.
+    pub insts: u64,
+}
+
+impl<'a, R: BufRead, W: Write> Machine<'a, R, W> {
+    /// Creates a new machine that is about to call `main`.
+    pub fn new(program: &'a Program, input: R, output: W) -> Self {
+        let mut mem = vec![0; MEM_SIZE as usize];
+        let base = DATA_BASE as usize;
+        mem[base..base + program.data.len()].copy_from_slice(&program.data);
+        let mut regs = [0; 32];
+        regs[RA] = EXIT_ADDR as i32;
+        regs[SP] = MEM_SIZE as i32;
+        Self {
+            program,
+            regs,
+            fregs: [0; 32],
+            mem,
+            pc: program.entry,
+            runtime: Runtime::new(input, output),
+            insts: 0,
+        }
+    }
+
+    /// Runs the program until `main` returns.
+    pub fn run(mut self) -> Result<Stats> {
+        loop {
+            let (inst, line) = match self.program.insts.get(self.pc) {
+                Some((inst, line)) => (inst, *line),
+                None => return Err(ErrorKind::InvalidJump(inst_addr(self.pc)).into()),
+            };
+            self.insts += 1;
+            self.pc += 1;
+            match self.exec(inst) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(kind) => return Err(Error::at(kind, line)),
+            }
+        }
+        let exit_code = self.regs[A0] & 0xff;
+        Ok(self.runtime.finish(exit_code, self.insts)?)
+    }
+
+    /// Executes an instruction, returns `false` if the program exits.
+    fn exec(&mut self, inst: &Inst) -> std::result::Result<bool, ErrorKind> {
+        match *inst {
+            Inst::Op(op, rd, rs1, rs2) => self.set_reg(rd, calc(op, self.reg(rs1), self.reg(rs2))),
+            Inst::OpImm(op, rd, rs, imm) => self.set_reg(rd, calc(op, self.reg(rs), imm)),
+            Inst::Li(rd, imm) => self.set_reg(rd, imm),
+            Inst::Load(width, rd, rs, offset) => {
+                let addr = self.addr(self.reg(rs).wrapping_add(offset), width.size())?;
+                let bytes = &self.mem[addr..addr + width.size()];
+                let value = match width {
+                    Width::Byte => bytes[0] as i8 as i32,
+                    Width::ByteU => bytes[0] as i32,
+                    Width::Half => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+                    Width::HalfU => u16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+                    Width::Word => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                };
+                self.set_reg(rd, value);
+            }
+            Inst::Store(width, rs2, rs1, offset) => {
+                let addr = self.addr(self.reg(rs1).wrapping_add(offset), width.size())?;
+                let bytes = self.reg(rs2).to_le_bytes();
+                self.mem[addr..addr + width.size()].copy_from_slice(&bytes[..width.size()]);
+            }
+            Inst::Branch(cond, rs1, rs2, target) => {
+                let (lhs, rhs) = (self.reg(rs1), self.reg(rs2));
+                let taken = match cond {
+                    Cond::Eq => lhs == rhs,
+                    Cond::Ne => lhs != rhs,
+                    Cond::Lt => lhs < rhs,
+                    Cond::Ge => lhs >= rhs,
+                    Cond::Ltu => (lhs as u32) < rhs as u32,
+                    Cond::Geu => lhs as u32 >= rhs as u32,
+                };
+                if taken {
+                    self.pc = target;
+                }
+            }
+            Inst::Jal(rd, target) => {
+                self.set_reg(rd, inst_addr(self.pc) as i32);
+                self.pc = target;
+            }
+            Inst::Jalr(rd, rs, offset) => {
+                let target = self.reg(rs).wrapping_add(offset) as u32 & !1;
+                self.set_reg(rd, inst_addr(self.pc) as i32);
+                if target == EXIT_ADDR {
+                    return Ok(false);
+                }
+                let index = (target.wrapping_sub(TEXT_BASE) / 4) as usize;
+                if target < TEXT_BASE
+                    || !target.is_multiple_of(4)
+                    || index >= self.program.insts.len()
+                {
+                    return Err(ErrorKind::InvalidJump(target));
+                }
+                self.pc = index;
+            }
+            Inst::Lib(func) => self.call_lib(func)?,
+            Inst::FLoad(fd, rs, offset) => {
+                let value = self.load(self.reg(rs).wrapping_add(offset))?;
+                self.fregs[fd] = value as u32;
+            }
+            Inst::FStore(fs, rs, offset) => {
+                self.store(self.reg(rs).wrapping_add(offset), self.fregs[fs] as i32)?;
+            }
+            Inst::FOp(op, fd, fs1, fs2) => {
+                let (lhs, rhs) = (self.freg(fs1), self.freg(fs2));
+                let value = match op {
+                    FOp::Add => lhs + rhs,
+                    FOp::Sub => lhs - rhs,
+                    FOp::Mul => lhs * rhs,
+                    FOp::Div => lhs / rhs,
+                };
+                self.fregs[fd] = float_bits(value);
+            }
+            Inst::FCmp(cmp, rd, fs1, fs2) => {
+                let (lhs, rhs) = (self.freg(fs1), self.freg(fs2));
+                let value = match cmp {
+                    FCmp::Eq => lhs == rhs,
+                    FCmp::Lt => lhs < rhs,
+                    FCmp::Le => lhs <= rhs,
+                };
+                self.set_reg(rd, value as i32);
+            }
+            Inst::FMvWX(fd, rs) => self.fregs[fd] = self.reg(rs) as u32,
+            Inst::FMvXW(rd, fs) => self.set_reg(rd, self.fregs[fs] as i32),
+            Inst::FCvtSW(fd, rs) => self.fregs[fd] = (self.reg(rs) as f32).to_bits(),
+            Inst::FCvtWS(rd, fs, rtz) => {
+                let value = self.freg(fs);
+                let value = if rtz { value } else { value.round_ties_even() };
+                self.set_reg(rd, float_to_int(value));
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the value of the given integer register.
+    pub fn reg(&self, reg: Reg) -> i32 {
+        self.regs[reg]
+    }
+
+    /// Sets the value of the given integer register, writes to `x0` are ignored.
+    pub fn set_reg(&mut self, reg: Reg, value: i32) {
+        if reg != 0 {
+            self.regs[reg] = value;
+        }
+    }
+
+    /// Returns the value of the given float register.
+    fn freg(&self, freg: FReg) -> f32 {
+        f32::from_bits(self.fregs[freg])
+    }
+
+    /// Overwrites all caller-saved registers, as a library call may do.
+    pub fn clobber_caller_saved(&mut self) {
+        for &reg in &CALLER_SAVED_REGS {
+            self.regs[reg] = CLOBBERED as i32;
+        }
+        for &freg in &CALLER_SAVED_FREGS {
+            self.fregs[freg] = CLOBBERED;
+        }
+    }
+
+    /// Checks the access of the given size at the given address,
+    /// returns the index in memory.
+    fn addr(&self, addr: i32, size: usize) -> std::result::Result<usize, ErrorKind> {
+        let addr = addr as u32;
+        let end = addr as u64 + size as u64;
+        if addr < DATA_BASE || end > MEM_SIZE as u64 || !(addr as usize).is_multiple_of(size) {
+            return Err(ErrorKind::InvalidAccess(addr));
+        }
+        Ok(addr as usize)
+    }
+
+    /// Loads a word from memory.
+    pub fn load(&self, addr: i32) -> std::result::Result<i32, ErrorKind> {
+        let addr = self.addr(addr, 4)?;
+        let bytes = &self.mem[addr..addr + 4];
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Stores a word to memory.
+    pub fn store(&mut self, addr: i32, value: i32) -> std::result::Result<(), ErrorKind> {
+        let addr = self.addr(addr, 4)?;
+        self.mem[addr..addr + 4].copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+}
+
+impl Width {
+    /// Returns the size of memory access in bytes.
+    fn size(self) -> usize {
+        match self {
+            Self::Byte | Self::ByteU => 1,
+            Self::Half | Self::HalfU => 2,
+            Self::Word => 4,
+        }
+    }
+}
+
+/// Calculates the result of an integer operation.
+fn calc(op: Op, lhs: i32, rhs: i32) -> i32 {
+    match op {
+        Op::Add => lhs.wrapping_add(rhs),
+        Op::Sub => lhs.wrapping_sub(rhs),
+        Op::Sll => lhs.wrapping_shl(rhs as u32),
+        Op::Slt => (lhs < rhs) as i32,
+        Op::Sltu => ((lhs as u32) < rhs as u32) as i32,
+        Op::Xor => lhs ^ rhs,
+        Op::Srl => (lhs as u32).wrapping_shr(rhs as u32) as i32,
+        Op::Sra => lhs.wrapping_shr(rhs as u32),
+        Op::Or => lhs | rhs,
+        Op::And => lhs & rhs,
+        Op::Mul => lhs.wrapping_mul(rhs),
+        Op::Mulh => ((lhs as i64 * rhs as i64) >> 32) as i32,
+        Op::Mulhsu => ((lhs as i64 * rhs as u32 as i64) >> 32) as i32,
+        Op::Mulhu => ((lhs as u32 as u64 * rhs as u32 as u64) >> 32) as i32,
+        // division by zero does not trap in RISC-V
+        Op::Div if rhs == 0 => -1,
+        Op::Div => lhs.wrapping_div(rhs),
+        Op::Divu if rhs == 0 => -1,
+        Op::Divu => (lhs as u32 / rhs as u32) as i32,
+        Op::Rem if rhs == 0 => lhs,
+        Op::Rem => lhs.wrapping_rem(rhs),
+        Op::Remu if rhs == 0 => lhs,
+        Op::Remu => (lhs as u32 % rhs as u32) as i32,
+    }
+}
@@ -0,0 +1,106 @@
+mod asm;
+mod machine;
+mod runtime;
+
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use sylib::Stats;
+
+/// Assembles the given RISC-V assembly and executes it from `main`,
+/// reads the input of SysY runtime functions from `input`,
+/// and writes their output to `output`.
+///
+/// Supports RV32IM, and the part of F extension used by the assembly generator.
+/// A pseudo instruction or a runtime function call counts as one executed
+/// instruction.
+pub fn run<R: BufRead, W: Write>(asm: &str, input: R, output: W) -> Result<Stats> {
+    let program = asm::assemble(asm)?;
+    machine::Machine::new(&program, input, output).run()
+}
+
+/// Result type of simulator.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error returned by simulator,
+/// with the line of assembly it refers to if there is one.
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub line: Option<usize>,
+}
+
+/// Kind of the error returned by simulator.
+#[derive(Debug)]
+pub enum ErrorKind {
+    UnknownInst(String),
+    UnknownDirective(String),
+    InvalidOperand(String),
+    ImmOutOfRange(i64),
+    UndefinedSymbol(String),
+    DuplicatedLabel(String),
+    InstInData,
+    DataInText,
+    NoMain,
+    DataTooLarge,
+    /// Out of bounds or misaligned memory access.
+    InvalidAccess(u32),
+    InvalidJump(u32),
+    Io(io::Error),
+}
+
+impl Error {
+    /// Creates a new error at the given line (0-based).
+    fn at(kind: ErrorKind, line: usize) -> Self {
+        Self {
+            kind,
+            line: Some(line),
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind, line: None }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        ErrorKind::Io(err).into()
+    }
+}
+
+impl From<io::Error> for ErrorKind {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line + 1)?;
+        }
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownInst(inst) => write!(f, "unknown instruction `{}`", inst),
+            Self::UnknownDirective(dir) => write!(f, "unknown directive `{}`", dir),
+            Self::InvalidOperand(opr) => write!(f, "invalid operand `{}`", opr),
+            Self::ImmOutOfRange(imm) => write!(f, "immediate `{}` out of range", imm),
+            Self::UndefinedSymbol(sym) => write!(f, "undefined symbol `{}`", sym),
+            Self::DuplicatedLabel(label) => write!(f, "duplicated label `{}`", label),
+            Self::InstInData => write!(f, "instruction in data section"),
+            Self::DataInText => write!(f, "data in text section"),
+            Self::NoMain => write!(f, "function `main` not found"),
+            Self::DataTooLarge => write!(f, "data section is too large"),
+            Self::InvalidAccess(addr) => write!(f, "invalid memory access at {:#010x}", addr),
+            Self::InvalidJump(addr) => write!(f, "invalid jump target {:#010x}", addr),
+            Self::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use super::machine::{Machine, A0, A1};
+use super::ErrorKind;
+use std::io::{BufRead, Write};
+use sylib::LibFunc;
+
+impl<R: BufRead, W: Write> Machine<'_, R, W> {
+    /// Calls the given library function, following the calling convention.
+    pub fn call_lib(&mut self, func: LibFunc) -> Result<(), ErrorKind> {
+        let a0 = self.reg(A0);
+        let a1 = self.reg(A1);
+        let ret = match func {
+            LibFunc::GetInt => Some(self.runtime.get_int()?),
+            LibFunc::GetCh => Some(self.runtime.get_ch()?),
+            LibFunc::GetFloat => Some(self.runtime.get_float()?.to_bits() as i32),
+            LibFunc::GetArray | LibFunc::GetFArray => {
+                let len = self.runtime.get_int()?;
+                for i in 0..len {
+                    let value = match func {
+                        LibFunc::GetArray => self.runtime.get_int()?,
+                        _ => self.runtime.get_float()?.to_bits() as i32,
+                    };
+                    self.store(a0.wrapping_add(i.wrapping_mul(4)), value)?;
+                }
+                Some(len)
+            }
+            LibFunc::PutInt => {
+                self.runtime.put_int(a0)?;
+                None
+            }
+            LibFunc::PutCh => {
+                self.runtime.put_ch(a0)?;
+                None
+            }
+            LibFunc::PutFloat => {
+                self.runtime.put_float(f32::from_bits(a0 as u32))?;
+                None
+            }
+            LibFunc::PutArray | LibFunc::PutFArray => {
+                let values = (0..a0.max(0))
+                    .map(|i| self.load(a1.wrapping_add(i.wrapping_mul(4))))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if func == LibFunc::PutArray {
+                    self.runtime.put_array(&values)?;
+                } else {
+                    let values: Vec<_> = values.iter().map(|&v| f32::from_bits(v as u32)).collect();
+                    self.runtime.put_farray(&values)?;
+                }
+                None
+            }
+            LibFunc::StartTime => {
+                self.runtime.start_time(self.insts);
+                None
+            }
+            LibFunc::StopTime => {
+                self.runtime.stop_time(self.insts);
+                None
+            }
+        };
+        self.clobber_caller_saved();
+        if let Some(ret) = ret {
+            self.set_reg(A0, ret);
+        }
+        Ok(())
+    }
+}
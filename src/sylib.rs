@@ -0,0 +1,236 @@
+use parse::hex_float;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// Functions in SysY runtime library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibFunc {
+    GetInt,
+    GetCh,
+    GetFloat,
+    GetArray,
+    GetFArray,
+    PutInt,
+    PutCh,
+    PutFloat,
+    PutArray,
+    PutFArray,
+    StartTime,
+    StopTime,
+}
+
+impl LibFunc {
+    /// Returns the library function of the given name, without `@`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "getint" => Self::GetInt,
+            "getch" => Self::GetCh,
+            "getfloat" => Self::GetFloat,
+            "getarray" => Self::GetArray,
+            "getfarray" => Self::GetFArray,
+            "putint" => Self::PutInt,
+            "putch" => Self::PutCh,
+            "putfloat" => Self::PutFloat,
+            "putarray" => Self::PutArray,
+            "putfarray" => Self::PutFArray,
+            "starttime" | "_sysy_starttime" => Self::StartTime,
+            "stoptime" | "_sysy_stoptime" => Self::StopTime,
+            _ => return None,
+        })
+    }
+}
+
+/// State of SysY runtime library, behaves like the C implementation.
+pub struct Runtime<R, W> {
+    input: R,
+    output: W,
+    /// Number of executed instructions when the timer started.
+    timer_start: Option<u64>,
+    timed_insts: Option<u64>,
+}
+
+impl<R: BufRead, W: Write> Runtime<R, W> {
+    /// Creates a new runtime.
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            timer_start: None,
+            timed_insts: None,
+        }
+    }
+
+    /// Reads a byte like `getch`, returns -1 at the end of input.
+    pub fn get_ch(&mut self) -> io::Result<i32> {
+        let byte = self.peek_byte()?;
+        if byte.is_some() {
+            self.input.consume(1);
+        }
+        Ok(byte.map_or(-1, i32::from))
+    }
+
+    /// Reads a decimal integer like `scanf("%d")`, returns 0 if there is none.
+    pub fn get_int(&mut self) -> io::Result<i32> {
+        let token = self.read_token(|b| b.is_ascii_digit() || b == b'-' || b == b'+')?;
+        let (neg, digits) = split_sign(&token);
+        let abs = digits
+            .bytes()
+            .take_while(u8::is_ascii_digit)
+            .fold(0i32, |v, d| {
+                v.wrapping_mul(10).wrapping_add((d - b'0') as i32)
+            });
+        Ok(if neg { abs.wrapping_neg() } else { abs })
+    }
+
+    /// Reads a decimal or hexadecimal float like `scanf("%a")`,
+    /// returns 0 if there is none.
+    pub fn get_float(&mut self) -> io::Result<f32> {
+        let token = self.read_token(|b| {
+            b.is_ascii_hexdigit() || b"+-.xXpP".contains(&b) || b == b'n' || b == b'i'
+        })?;
+        let (neg, abs) = split_sign(&token);
+        let value = if abs.starts_with("0x") || abs.starts_with("0X") {
+            parse_hex_float(abs)
+        } else {
+            abs.parse().ok()
+        }
+        .unwrap_or(0.0);
+        Ok(if neg { -value } else { value })
+    }
+
+    pub fn put_int(&mut self, value: i32) -> io::Result<()> {
+        write!(self.output, "{}", value)
+    }
+
+    pub fn put_ch(&mut self, value: i32) -> io::Result<()> {
+        self.output.write_all(&[value as u8])
+    }
+
+    pub fn put_float(&mut self, value: f32) -> io::Result<()> {
+        write!(self.output, "{}", format_float(value))
+    }
+
+    /// Writes an integer array like `putarray`.
+    pub fn put_array(&mut self, values: &[i32]) -> io::Result<()> {
+        write!(self.output, "{}:", values.len())?;
+        for value in values {
+            write!(self.output, " {}", value)?;
+        }
+        writeln!(self.output)
+    }
+
+    /// Writes a float array like `putfarray`.
+    pub fn put_farray(&mut self, values: &[f32]) -> io::Result<()> {
+        write!(self.output, "{}:", values.len())?;
+        for &value in values {
+            write!(self.output, " {}", format_float(value))?;
+        }
+        writeln!(self.output)
+    }
+
+    /// Starts the timer, `insts` is the number of executed instructions.
+    pub fn start_time(&mut self, insts: u64) {
+        self.timer_start = Some(insts);
+    }
+
+    /// Stops the timer, accumulates instructions executed since it started.
+    pub fn stop_time(&mut self, insts: u64) {
+        if let Some(start) = self.timer_start.take() {
+            *self.timed_insts.get_or_insert(0) += insts - start;
+        }
+    }
+
+    /// Flushes the output, returns the statistics of the execution.
+    pub fn finish(mut self, exit_code: i32, insts: u64) -> io::Result<Stats> {
+        self.output.flush()?;
+        Ok(Stats {
+            exit_code,
+            insts,
+            timed_insts: self.timed_insts,
+        })
+    }
+
+    /// Returns the next byte in the input without consuming it.
+    fn peek_byte(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.input.fill_buf()?.first().copied())
+    }
+
+    /// Skips whitespaces, then reads bytes while they satisfy the given predicate.
+    fn read_token(&mut self, pred: fn(u8) -> bool) -> io::Result<String> {
+        while self.peek_byte()?.is_some_and(|b| b.is_ascii_whitespace()) {
+            self.input.consume(1);
+        }
+        let mut token = String::new();
+        while let Some(byte) = self.peek_byte()?.filter(|&b| pred(b)) {
+            token.push(byte as char);
+            self.input.consume(1);
+        }
+        Ok(token)
+    }
+}
+
+/// Statistics of an execution.
+#[derive(Debug)]
+pub struct Stats {
+    /// Exit code, i.e. the lowest 8 bits of the return value of `main`.
+    pub exit_code: i32,
+    /// Number of executed instructions.
+    pub insts: u64,
+    /// Number of instructions executed between `starttime` and `stoptime`,
+    /// `None` if the timer is never used.
+    pub timed_insts: Option<u64>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "exit code: {}", self.exit_code)?;
+        write!(f, "instructions: {}", self.insts)?;
+        if let Some(timed) = self.timed_insts {
+            write!(f, "\ntimed instructions: {}", timed)?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the sign from a number, returns if it is negative and the rest.
+fn split_sign(token: &str) -> (bool, &str) {
+    match token.as_bytes().first() {
+        Some(b'-') => (true, &token[1..]),
+        Some(b'+') => (false, &token[1..]),
+        _ => (false, token),
+    }
+}
+
+/// Parses a hexadecimal float with prefix `0x`, exponent can be omitted.
+fn parse_hex_float(text: &str) -> Option<f32> {
+    let (mantissa, exp) = match text[2..].split_once(['p', 'P']) {
+        Some((mantissa, exp)) => (mantissa, exp),
+        None => (&text[2..], "0"),
+    };
+    let valid = mantissa.bytes().any(|b| b.is_ascii_hexdigit())
+        && mantissa.bytes().all(|b| b.is_ascii_hexdigit() || b == b'.')
+        && mantissa.matches('.').count() <= 1
+        && exp.parse::<i32>().is_ok();
+    valid.then(|| hex_float(&format!("0x{}p{}", mantissa, exp)) as f32)
+}
+
+/// Formats a float like `printf("%a")` of glibc.
+fn format_float(value: f32) -> String {
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value.is_nan() {
+        return format!("{}nan", sign);
+    }
+    if value.is_infinite() {
+        return format!("{}inf", sign);
+    }
+    if value == 0.0 {
+        return format!("{}0x0p+0", sign);
+    }
+    // floats are promoted to double, which makes subnormal floats normal
+    let bits = (value as f64).to_bits();
+    let exp = ((bits >> 52) & 0x7ff) as i32 - 1023;
+    let frac = format!("{:013x}", bits & ((1 << 52) - 1));
+    let frac = frac.trim_end_matches('0');
+    let dot = if frac.is_empty() { "" } else { "." };
+    format!("{}0x1{}{}p{:+}", sign, dot, frac, exp)
+}
@@ -0,0 +1,103 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Compiler phases whose internal information can be traced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Parser, dumps the AST.
+    Parse,
+    /// Semantic analysis, dumps definitions in scopes and the typed AST.
+    Sema,
+    /// IR generator, dumps Koopa IR values bound to symbols.
+    Irgen,
+    /// Optimizer, dumps the passes being run.
+    Opt,
+    /// Assembly generator, dumps stack slot layout, registers and labels.
+    Asmgen,
+}
+
+impl Phase {
+    /// All phases, in the order they run.
+    pub const ALL: &'static [Phase] = &[
+        Self::Parse,
+        Self::Sema,
+        Self::Irgen,
+        Self::Opt,
+        Self::Asmgen,
+    ];
+
+    /// Returns the phase of the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.name() == name)
+    }
+
+    /// Returns the name of the phase.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Sema => "sema",
+            Self::Irgen => "irgen",
+            Self::Opt => "opt",
+            Self::Asmgen => "asmgen",
+        }
+    }
+}
+
+/// Enabled phases and the sink of traces.
+struct Tracer {
+    phases: Vec<Phase>,
+    out: Box<dyn Write>,
+}
+
+thread_local! {
+    static TRACER: RefCell<Option<Tracer>> = const { RefCell::new(None) };
+}
+
+/// Writes a line of trace to the sink if the given phase is enabled.
+/// Arguments are not evaluated if the phase is disabled.
+macro_rules! trace {
+    ($phase:ident, $($arg:tt)*) => {
+        if $crate::trace::is_enabled($crate::trace::Phase::$phase) {
+            $crate::trace::write($crate::trace::Phase::$phase, format_args!($($arg)*));
+        }
+    };
+}
+
+/// Enables tracing of the given phases on the current thread,
+/// traces are written to `out`. Tracing is off by default.
+pub fn enable(phases: &[Phase], out: Box<dyn Write>) {
+    let tracer = Tracer {
+        phases: phases.to_vec(),
+        out,
+    };
+    TRACER.with(|t| *t.borrow_mut() = Some(tracer));
+}
+
+/// Disables tracing, flushes the sink.
+pub fn disable() -> io::Result<()> {
+    match TRACER.with(|t| t.borrow_mut().take()) {
+        Some(mut tracer) => tracer.out.flush(),
+        None => Ok(()),
+    }
+}
+
+/// Returns `true` if tracing of the given phase is enabled.
+pub fn is_enabled(phase: Phase) -> bool {
+    TRACER.with(|t| {
+        t.borrow()
+            .as_ref()
+            .is_some_and(|t| t.phases.contains(&phase))
+    })
+}
+
+/// Writes a line of trace of the given phase, prefixed by the phase name.
+///
+/// Traces are diagnostics, so errors on writing are ignored.
+pub fn write(phase: Phase, args: fmt::Arguments) {
+    TRACER.with(|t| {
+        if let Some(tracer) = t.borrow_mut().as_mut() {
+            let _ = writeln!(tracer.out, "[{}] {}", phase.name(), args);
+        }
+    });
+}
@@ -0,0 +1,37 @@
+//! Fixtures shared by integration tests.
+
+// each test uses only part of the fixtures
+#![allow(dead_code)]
+
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+
+/// A test program, with the input of SysY runtime functions.
+pub struct Fixture {
+    pub name: String,
+    pub text: String,
+    pub input: String,
+    /// Expected output, followed by the exit code on its own line.
+    pub expected: Option<String>,
+}
+
+/// Returns all fixtures in the given directory under `tests`
+/// with the given extension, sorted by names.
+pub fn fixtures(dir: &str, ext: &str) -> Vec<Fixture> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join(dir);
+    let mut paths: Vec<_> = read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == ext))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| Fixture {
+            name: path.file_name().unwrap().to_string_lossy().into(),
+            text: read_to_string(&path).unwrap(),
+            input: read_to_string(path.with_extension("in")).unwrap_or_default(),
+            expected: read_to_string(path.with_extension("out")).ok(),
+        })
+        .collect()
+}
@@ -0,0 +1,189 @@
+//! Checks the rendered diagnostics of syntax errors, semantic errors
+//! and warnings, and the lint options of the compiler binary.
+
+extern crate compiler;
+
+use compiler::diag::{Level, SourceFile};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Compiles the source, returns the rendered errors.
+fn errors(src: &str) -> String {
+    let err = compiler::compile(src).map(|_| ()).unwrap_err();
+    err.render(&SourceFile::new("test.sy", src))
+}
+
+/// Compiles the source, returns the locations and messages of all warnings.
+fn warnings(src: &str) -> Vec<String> {
+    let source = SourceFile::new("test.sy", src);
+    let warnings = compiler::compile(src).unwrap().1;
+    let loc = |pos| source.line_col(pos);
+    let warnings = warnings.iter().map(|w| (loc(w.span.start), w.to_string()));
+    warnings.map(|((line, col), msg)| format!("{}:{}: {}", line, col, msg)).collect()
+}
+
+/// Compiles the source from the standard input by the compiler binary
+/// with the given options, returns the exit code and the standard error.
+fn run_compiler(src: &str, options: &[&str]) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .args(["-koopa", "-", "-o", "-"])
+        .args(options)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(src.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn error_location() {
+    let src = "int main() {\n  int x = 1;\n  return y + x;\n}\n";
+    let expected = r"test.sy:3:10: error: symbol `y` not found
+  |
+3 |   return y + x;
+  |          ^
+aborting due to 1 error";
+    assert_eq!(errors(src), expected);
+}
+
+#[test]
+fn syntax_errors_recovered() {
+    let src = "int main() {\n  int x = ;\n  x = x + 1\n  return x;\n}\n";
+    let expected = "test.sy:2:11: error: unexpected `;`, expected one of `!`, `(`, `+`, `-`, `{`, \
+float literal, integer literal or identifier
+  |
+2 |   int x = ;
+  |           ^
+test.sy:4:3: error: unexpected `return`, expected one of `!=`, `%`, `&&`, `*`, `+`, `-`, `/`, \
+`;`, `<`, `<=`, `==`, `>`, `>=` or `||`
+  |
+4 |   return x;
+  |   ^^^^^^
+aborting due to 2 syntax errors";
+    assert_eq!(errors(src), expected);
+}
+
+#[test]
+fn semantic_errors_in_order() {
+    let src = "int f(int a[]);
+const int C = 1;
+int main() {
+  C = 2;
+  break;
+  return f(1, 2) + g();
+}
+int f(int a) { return a; }
+";
+    let expected = r"test.sy:4:3: error: assigning to constant `C`
+  |
+4 |   C = 2;
+  |   ^
+test.sy:5:3: error: using break/continue outside of loop
+  |
+5 |   break;
+  |   ^^^^^^
+test.sy:6:10: error: argument mismatch in call to `f`
+  |
+6 |   return f(1, 2) + g();
+  |          ^^^^^^^
+test.sy:6:20: error: symbol `g` not found
+  |
+6 |   return f(1, 2) + g();
+  |                    ^^^
+test.sy:8:1: error: signature of function `f` does not match its declaration
+  |
+8 | int f(int a) { return a; }
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^^
+test.sy:1:1: note: previously declared here
+  |
+1 | int f(int a[]);
+  | ^^^^^^^^^^^^^^^
+aborting due to 5 errors";
+    assert_eq!(errors(src), expected);
+}
+
+#[test]
+fn prototype_before_definition() {
+    let src = "int even(int n);
+int odd(int n) { if (n == 0) return 0; return even(n - 1); }
+int even(int n) { if (n == 0) return 1; return odd(n - 1); }
+int main() { return even(10); }
+";
+    let (program, warnings) = compiler::compile(src).unwrap();
+    assert!(warnings.is_empty());
+    let stats = compiler::interp::run(&program, "".as_bytes(), Vec::new()).unwrap();
+    assert_eq!(stats.exit_code, 1);
+}
+
+/// Source with warnings of all lints.
+const WARNINGS: &str = "int f(int p) {
+  int unused = 1;
+  if (p) return 1;
+}
+int main() {
+  int x = 0;
+  {
+    int x = 1;
+    putint(x);
+  }
+  int u;
+  putint(u);
+  return x;
+  putint(2);
+}
+";
+
+#[test]
+fn warnings_in_order() {
+    let expected = [
+        "1:1: function `f` is never called [-Wunused]",
+        "2:7: unused variable `unused` [-Wunused]",
+        "4:1: non-void function `f` may reach its end without returning [-Wreturn]",
+        "8:9: declaration of `x` shadows an outer declaration [-Wshadow]",
+        "12:10: variable `u` may be used uninitialized [-Wuninit]",
+        "14:3: unreachable statement [-Wunreachable]",
+    ];
+    assert_eq!(warnings(WARNINGS), expected);
+}
+
+#[test]
+fn rendered_warning() {
+    let src = "int main() {\n  int x;\n  return 0;\n}\n";
+    let source = SourceFile::new("test.sy", src);
+    let warnings = compiler::compile(src).unwrap().1;
+    let rendered: Vec<_> = warnings
+        .iter()
+        .map(|w| source.diagnostic(Level::Warning, w.span, w).to_string())
+        .collect();
+    let expected = r"test.sy:2:7: warning: unused variable `x` [-Wunused]
+  |
+2 |   int x;
+  |       ^";
+    assert_eq!(rendered, [expected]);
+}
+
+#[test]
+fn lint_options() {
+    let (code, stderr) = run_compiler(WARNINGS, &[]);
+    assert_eq!(code, 0);
+    assert_eq!(stderr.matches("warning:").count(), 5);
+    assert!(!stderr.contains("[-Wshadow]"));
+    let (code, stderr) = run_compiler(WARNINGS, &["-Wall", "-Wno-unused"]);
+    assert_eq!(code, 0);
+    assert_eq!(stderr.matches("warning:").count(), 4);
+    assert!(stderr.contains("[-Wshadow]") && !stderr.contains("[-Wunused]"));
+}
+
+#[test]
+fn werror() {
+    let (code, stderr) = run_compiler(WARNINGS, &["-Werror"]);
+    assert_ne!(code, 0);
+    assert!(stderr.starts_with("<stdin>:1:1: warning: function `f` is never called [-Wunused]"));
+    assert!(stderr.ends_with("aborting due to 5 warnings (`-Werror`)\n"), "{}", stderr);
+    let lints = ["-Wno-unused", "-Wno-return", "-Wno-uninit", "-Wno-unreachable"];
+    let (code, _) = run_compiler(WARNINGS, &[&lints[..], &["-Werror"]].concat());
+    assert_eq!(code, 0);
+}
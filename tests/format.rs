@@ -0,0 +1,39 @@
+//! Checks that formatted SysY sources parse into the same ASTs.
+
+extern crate compiler;
+
+mod common;
+
+use common::fixtures;
+use compiler::dump::ToNode;
+use compiler::pretty::Printer;
+
+#[test]
+fn reparse_formatted() {
+    for fixture in fixtures("sysy", "sy") {
+        let ast = compiler::parse(&fixture.text).unwrap();
+        let formatted = Printer::new(4).print(&ast);
+        let reparsed = compiler::parse(&formatted)
+            .unwrap_or_else(|_| panic!("{}: formatted source has syntax errors", fixture.name));
+        assert!(
+            reparsed.to_node().without_spans() == ast.to_node().without_spans(),
+            "{}: formatted source parses into a different AST",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn format_idempotent() {
+    for fixture in fixtures("sysy", "sy") {
+        let formatted = compiler::format(&fixture.text, 2).unwrap();
+        let again = compiler::format(&formatted, 2).unwrap();
+        assert_eq!(formatted, again, "{}: formatting is not idempotent", fixture.name);
+    }
+}
+
+#[test]
+fn float_literals_unchanged() {
+    let src = "float a = 0x1.8p1, b = 1e40, c = 1e-50, d = 03.50;\n";
+    assert_eq!(compiler::format(src, 4).unwrap(), src);
+}
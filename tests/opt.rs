@@ -0,0 +1,135 @@
+//! Checks that optimization passes do not change the behavior of programs,
+//! by interpreting the programs before and after running the passes.
+//! Unoptimized programs are checked against the expected outputs,
+//! and some passes are checked to take effect on the fixtures.
+
+extern crate compiler;
+extern crate koopa;
+
+mod common;
+
+use common::{fixtures, Fixture};
+use compiler::interp;
+use compiler::opt::OptLevel;
+use koopa::ir::{Program, ValueKind};
+
+/// All optimization passes.
+const PASSES: &[&str] = &["mem2reg", "fold", "simplify-cfg", "dce", "sccp", "gvn", "licm"];
+
+/// Interprets the program, returns its output and exit code.
+fn run(program: &Program, input: &str) -> (String, i32) {
+    let mut output = Vec::new();
+    let stats = interp::run(program, input.as_bytes(), &mut output).unwrap();
+    (String::from_utf8(output).unwrap(), stats.exit_code)
+}
+
+/// Returns the fixture in `tests/koopa` with the given name.
+fn koopa_fixture(name: &str) -> Fixture {
+    let fixture = fixtures("koopa", "koopa").into_iter().find(|f| f.name == name);
+    fixture.unwrap()
+}
+
+/// Counts the instructions in the function with the given name,
+/// whose kinds satisfy the predicate.
+fn count_insts<F: Fn(&ValueKind) -> bool>(program: &Program, name: &str, pred: F) -> usize {
+    let data = program.funcs().values().find(|d| d.name() == name).unwrap();
+    let insts = data.layout().bbs().nodes().flat_map(|node| node.insts().keys());
+    insts.filter(|&&inst| pred(data.dfg().value(inst).kind())).count()
+}
+
+/// Formats the output and exit code as in expected output files.
+fn format_result((output, exit_code): &(String, i32)) -> String {
+    let newline = if output.is_empty() || output.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}{}\n", output, newline, exit_code)
+}
+
+/// Checks that each pass, each pass after `mem2reg` and the passes of
+/// each optimization level do not change the output and exit code of
+/// the program created by `build`.
+fn check<F: Fn() -> Program>(fixture: &Fixture, build: F) {
+    let expected = run(&build(), &fixture.input);
+    if let Some(out) = &fixture.expected {
+        assert_eq!(&format_result(&expected), out, "{}: unexpected result", fixture.name);
+    }
+    let mut pipelines: Vec<Vec<&str>> = Vec::new();
+    for &pass in PASSES {
+        pipelines.push(vec![pass]);
+        pipelines.push(vec!["mem2reg", pass]);
+    }
+    for level in OptLevel::ALL {
+        pipelines.push(level.passes().to_vec());
+    }
+    for passes in pipelines {
+        let mut program = build();
+        compiler::optimize(&mut program, &passes).unwrap();
+        let result = run(&program, &fixture.input);
+        assert_eq!(result, expected, "{}: `{}`", fixture.name, passes.join(","));
+    }
+}
+
+#[test]
+fn sysy_fixtures() {
+    for fixture in fixtures("sysy", "sy") {
+        check(&fixture, || compiler::compile(&fixture.text).unwrap().0);
+    }
+}
+
+#[test]
+fn koopa_fixtures() {
+    for fixture in fixtures("koopa", "koopa") {
+        check(&fixture, || compiler::parse_koopa(&fixture.text).unwrap());
+    }
+}
+
+#[test]
+fn unbounded_recursion() {
+    let src = "int f(int n) { return f(n + 1); }\nint main() { return f(0); }\n";
+    let mut program = compiler::compile(src).unwrap().0;
+    compiler::optimize(&mut program, OptLevel::O1.passes()).unwrap();
+    let result = interp::run(&program, "".as_bytes(), Vec::new());
+    assert!(matches!(result, Err(interp::Error::StackOverflow)));
+}
+
+#[test]
+fn licm_hoists_invariants() {
+    let fixture = koopa_fixture("licm.koopa");
+    let run_insts = |passes: &[&str]| {
+        let mut program = compiler::parse_koopa(&fixture.text).unwrap();
+        compiler::optimize(&mut program, passes).unwrap();
+        interp::run(&program, "".as_bytes(), Vec::new()).unwrap().insts
+    };
+    assert!(run_insts(&["licm"]) < run_insts(&[]));
+}
+
+#[test]
+fn sccp_folds_constant_branch() {
+    let fixture = koopa_fixture("branch.koopa");
+    let mut program = compiler::parse_koopa(&fixture.text).unwrap();
+    let is_branch = |kind: &ValueKind| matches!(kind, ValueKind::Branch(_));
+    assert_eq!(count_insts(&program, "@main", is_branch), 1);
+    compiler::optimize(&mut program, &["sccp"]).unwrap();
+    assert_eq!(count_insts(&program, "@main", is_branch), 0);
+}
+
+#[test]
+fn gvn_numbers_repeated_load() {
+    let text = "global @g = alloc i32, 3
+
+fun @main(): i32 {
+%entry:
+  %x = load @g
+  %y = load @g
+  %s = add %x, %y
+  %t = add %x, %y
+  %r = mul %s, %t
+  ret %r
+}
+";
+    let mut program = compiler::parse_koopa(text).unwrap();
+    compiler::optimize(&mut program, &["gvn"]).unwrap();
+    let is_load = |kind: &ValueKind| matches!(kind, ValueKind::Load(_));
+    assert_eq!(count_insts(&program, "@main", is_load), 1);
+    let is_binary = |kind: &ValueKind| matches!(kind, ValueKind::Binary(_));
+    assert_eq!(count_insts(&program, "@main", is_binary), 2);
+    assert_eq!(run(&program, ""), (String::new(), 36));
+}
@@ -0,0 +1,50 @@
+//! Checks the generated RISC-V assembly, by running it in the simulator
+//! and comparing with the interpreter running the Koopa IR.
+//! Also checks errors reported by the simulator.
+
+extern crate compiler;
+
+mod common;
+
+use common::fixtures;
+use compiler::opt::OptLevel;
+use compiler::{interp, sim};
+
+#[test]
+fn asm_matches_interp() {
+    for fixture in fixtures("sysy", "sy") {
+        for &level in OptLevel::ALL {
+            let name = format!("{} at {:?}", fixture.name, level);
+            let mut program = compiler::compile(&fixture.text).unwrap().0;
+            compiler::optimize(&mut program, level.passes()).unwrap();
+            let mut expected = Vec::new();
+            let stats = interp::run(&program, fixture.input.as_bytes(), &mut expected).unwrap();
+            let mut asm = Vec::new();
+            compiler::generate_asm(&program, &mut asm).unwrap();
+            let asm = String::from_utf8(asm).unwrap();
+            let mut output = Vec::new();
+            let result = sim::run(&asm, fixture.input.as_bytes(), &mut output)
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(result.exit_code, stats.exit_code, "{}: exit code", name);
+            assert!(output == expected, "{}: output differs", name);
+        }
+    }
+}
+
+#[test]
+fn invalid_access() {
+    let asm = "  .text\n  .globl main\nmain:\n  li t0, 8\n  lw a0, 0(t0)\n  ret\n";
+    let err = sim::run(asm, "".as_bytes(), Vec::new()).unwrap_err();
+    assert!(matches!(err.kind, sim::ErrorKind::InvalidAccess(8)), "{:?}", err);
+    assert_eq!(err.line, Some(4));
+}
+
+#[test]
+fn out_of_bounds_index() {
+    let src = "int a[4];\nint main() { return a[-100000]; }\n";
+    let program = compiler::compile(src).unwrap().0;
+    let mut asm = Vec::new();
+    compiler::generate_asm(&program, &mut asm).unwrap();
+    let err = sim::run(&String::from_utf8(asm).unwrap(), "".as_bytes(), Vec::new()).unwrap_err();
+    assert!(matches!(err.kind, sim::ErrorKind::InvalidAccess(_)), "{:?}", err);
+}